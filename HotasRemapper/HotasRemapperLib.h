@@ -9,6 +9,7 @@
 #define HotasRemapperLib_h
 
 #include <stdbool.h>
+#include <stdint.h>
 #include <stdio.h>
 
 enum ConnectionType {
@@ -22,6 +23,7 @@ void* OpenLib(const char* settings_ptr,
               void (*connection_status_callback)(
                   enum ConnectionType connection_type, bool is_connected));
 bool LoadInputRemapping(void* lib_handle, const char* input_remapping_ptr);
+uint64_t GetNumSuppressedKeyPresses(void* lib_handle);
 void CloseLib(void* lib_handle);
 
 #endif /* HotasRemapperLib_h */
@@ -5,16 +5,21 @@ use std::ffi::c_char;
 use std::ffi::c_void;
 use std::marker::PhantomPinned;
 use std::pin::Pin;
+use std::time::Duration;
+use std::time::Instant;
 
 use anyhow::anyhow;
 use anyhow::bail;
 use anyhow::Result;
+use core_foundation::runloop::CFRunLoopTimerCallBack;
+use core_foundation::runloop::CFRunLoopTimerRef;
 use io_kit_sys::hid::base::IOHIDDeviceCallback;
 use io_kit_sys::hid::base::IOHIDDeviceRef;
 use io_kit_sys::hid::base::IOHIDValueCallback;
 use io_kit_sys::hid::base::IOHIDValueRef;
 use io_kit_sys::ret::IOReturn;
 use protobuf::text_format::parse_from_str as parse_proto_from_str;
+use protobuf::text_format::print_to_string as print_proto_to_string;
 
 use crate::input_reader::hid_device::DeviceProperty;
 use crate::input_reader::hid_device::DeviceType as HIDDeviceType;
@@ -23,14 +28,20 @@ use crate::input_reader::hid_device::HandleInputEvent;
 use crate::input_reader::hid_manager::HIDManager;
 use crate::input_reader::hid_manager::HandleDeviceEvent;
 use crate::input_remapper::InputRemapper;
+use crate::run_loop_timer::HandleTimerEvent;
+use crate::run_loop_timer::RunLoopTimer;
+use crate::settings::InputRemapperSettings;
 use crate::settings::Settings;
 use crate::utils::new_string_from_ptr;
 use crate::virtual_device::VirtualDevice;
 use crate::ConnectionStatusCallback;
 use crate::ConnectionType;
 
+const DEFAULT_TIMER_INTERVAL_MS: i32 = 5;
+
 pub(crate) struct DeviceManager {
     hid_manager: HIDManager,
+    timer: RunLoopTimer,
     hid_devices: RefCell<HashMap<IOHIDDeviceRef, HIDDevice>>,
     virtual_deivce: VirtualDevice,
     input_remapper: InputRemapper,
@@ -58,25 +69,31 @@ impl DeviceManager {
         };
         println!("Initializing with settings: {}", dump_settings(&settings));
 
+        let timer_interval =
+            get_timer_interval(&settings.input_remapper_settings)?;
+        let virtual_deivce = VirtualDevice::new(
+            &settings.virtual_device_settings,
+            connection_status_callback,
+        )?;
+        let input_remapper = InputRemapper::new(virtual_deivce.output_kinds());
         let manager = Box::pin(Self {
             hid_manager: HIDManager::new(&settings.input_reader_settings)?,
+            timer: RunLoopTimer::new(timer_interval),
             hid_devices: Default::default(),
-            virtual_deivce: VirtualDevice::new(
-                &settings.virtual_device_settings,
-                connection_status_callback,
-            )?,
-            input_remapper: InputRemapper::new(),
+            virtual_deivce,
+            input_remapper,
             connection_status_callback,
             _pinned_marker: PhantomPinned,
         });
         // Safe because we won't move `DeviceManager` out of the pinned object,
-        // and it outlives its member `HIDManager`.
+        // and it outlives its members `HIDManager` and `RunLoopTimer`.
         unsafe {
             let pinned_manager_ptr = &*manager.as_ref() as *const Self;
             manager
                 .as_ref()
                 .hid_manager
                 .set_device_callbacks(pinned_manager_ptr);
+            manager.as_ref().timer.start(pinned_manager_ptr);
         }
         Ok(manager)
     }
@@ -89,10 +106,23 @@ impl DeviceManager {
     ) -> Result<()> {
         let encoded_input_remapping = new_string_from_ptr(input_remapping_ptr)
             .map_err(|e| anyhow!("Invalid input_remapping_ptr: {}", e))?;
+        println!(
+            "Key presses suppressed by rate limiting so far: {}",
+            self.num_suppressed_key_presses()
+        );
+        self.virtual_deivce.release_all_keys();
         self.input_remapper
             .load_input_remapping(&encoded_input_remapping)
     }
 
+    /// Returns how many key presses were dropped by `key_rate_limit` of the
+    /// virtual device, plus those dropped by the `rate_limit` of inputs since
+    /// the input remapping was loaded.
+    pub fn num_suppressed_key_presses(&self) -> u64 {
+        self.virtual_deivce.num_suppressed_key_presses()
+            + self.input_remapper.num_suppressed_key_presses()
+    }
+
     fn report_connection_status(
         &self,
         device_type: HIDDeviceType,
@@ -159,16 +189,25 @@ impl DeviceManager {
                 if let Some(input_event) =
                     device.interpret_raw_input_event(raw_input_event)
                 {
-                    if let Some(key_event) =
+                    for (source, output_event) in
                         self.input_remapper.remap_input_event(&input_event)
                     {
-                        self.virtual_deivce.send_key_event(key_event);
+                        self.virtual_deivce
+                            .send_output_event(&source, output_event);
                     }
                 }
                 return;
             }
         }
     }
+
+    fn handle_timer_fired(&self) {
+        let now = Instant::now();
+        for (source, output_event) in self.input_remapper.tick(now) {
+            self.virtual_deivce.send_output_event(&source, output_event);
+        }
+        self.virtual_deivce.tick(now);
+    }
 }
 
 impl HandleDeviceEvent for DeviceManager {
@@ -187,6 +226,12 @@ impl HandleInputEvent for DeviceManager {
     }
 }
 
+impl HandleTimerEvent for DeviceManager {
+    fn timer_fired_callback() -> CFRunLoopTimerCallBack {
+        handle_timer_fired
+    }
+}
+
 impl From<HIDDeviceType> for ConnectionType {
     fn from(value: HIDDeviceType) -> Self {
         match value {
@@ -207,6 +252,17 @@ unsafe fn load_settings(settings_ptr: *const c_char) -> Result<Settings> {
         .map_err(|e| anyhow!("Failed to parse as text proto: {}", e))
 }
 
+fn get_timer_interval(settings: &InputRemapperSettings) -> Result<Duration> {
+    let timer_interval_ms = match settings.timer_interval_ms {
+        0 => DEFAULT_TIMER_INTERVAL_MS,
+        interval_ms if interval_ms < 0 => {
+            bail!("timer_interval_ms must be non-negative!")
+        }
+        interval_ms => interval_ms,
+    };
+    Ok(Duration::from_millis(timer_interval_ms as u64))
+}
+
 extern "C" fn handle_device_matched(
     context: *mut c_void,
     _result: IOReturn,
@@ -246,19 +302,62 @@ extern "C" fn handle_input_received(
     }
 }
 
+extern "C" fn handle_timer_fired(_timer: CFRunLoopTimerRef, info: *mut c_void) {
+    // Safe because we stored a pointer to a pinned `DeviceManager`.
+    if let Some(manager) = unsafe { (info as *const DeviceManager).as_ref() } {
+        manager.handle_timer_fired();
+    }
+}
+
 fn dump_settings(settings: &Settings) -> String {
     format!(
         "
 \tJoystick device name: {:?}
 \tThrottle device name: {:?}
+\tTimer interval (ms): {}
+\tTransport: {:?}
 \tVirtual device MAC address: {}
 \tRFCOMM channel ID: {}
-\tRate limiting threshold (ms): {}
+\tNetwork address: {:?}
+\tReconnect interval (ms): {}
+\tSerial port path: {:?}
+\tSerial baud rate: {}
+\tKey rate limit: {}
+\tMin key down duration (ms): {}
+\tMin report interval (ms): {}
+\tKeyboard report format: {:?}
+\tAdditional outputs: {:?}
 ",
         settings.input_reader_settings.joystick_device_name,
         settings.input_reader_settings.throttle_device_name,
+        settings.input_remapper_settings.timer_interval_ms,
+        settings
+            .virtual_device_settings
+            .transport_type
+            .enum_value_or_default(),
         settings.virtual_device_settings.mac_address,
         settings.virtual_device_settings.rfcomm_channel_id,
-        settings.virtual_device_settings.rate_limiting_threshold_ms,
+        settings.virtual_device_settings.network_address,
+        settings.virtual_device_settings.reconnect_interval_ms,
+        settings.virtual_device_settings.serial_port_path,
+        settings.virtual_device_settings.serial_baud_rate,
+        settings
+            .virtual_device_settings
+            .key_rate_limit
+            .as_ref()
+            .map(|rate_limit| print_proto_to_string(rate_limit))
+            .unwrap_or_else(|| "none".to_string()),
+        settings.virtual_device_settings.min_key_down_ms,
+        settings.virtual_device_settings.min_report_interval_ms,
+        settings
+            .virtual_device_settings
+            .keyboard_report_format
+            .enum_value_or_default(),
+        settings
+            .virtual_device_settings
+            .additional_outputs
+            .iter()
+            .map(|output| output.enum_value())
+            .collect::<Vec<_>>(),
     )
 }
@@ -29,6 +29,7 @@ use io_kit_sys::hid::value::IOHIDValueGetElement;
 use io_kit_sys::hid::value::IOHIDValueGetIntegerValue;
 
 use super::hid_device_input::DeviceInput;
+use super::hid_device_input::HatSwitchRange;
 use super::hid_device_input::InputType;
 use crate::utils::new_cf_string_from_ptr;
 use crate::utils::new_string_from_cf_string;
@@ -110,10 +111,16 @@ pub(crate) struct InputEvent {
     pub value: i32,
 }
 
+struct InputElement {
+    device_input: DeviceInput,
+    // Only set for hat switches.
+    hat_switch_range: Option<HatSwitchRange>,
+}
+
 /// A struct wrapping `IOHIDDeviceRef` from IOKit.
 pub(crate) struct HIDDevice {
     device_type: DeviceType,
-    input_map: HashMap<IOHIDElementCookie, DeviceInput>,
+    input_map: HashMap<IOHIDElementCookie, InputElement>,
 }
 
 impl HIDDevice {
@@ -144,12 +151,17 @@ impl HIDDevice {
         input_event: RawInputEvent,
     ) -> Option<InputEvent> {
         match self.input_map.get(&input_event.input_id) {
-            Some(device_input) => {
+            Some(input_element) => {
+                let device_input = input_element.device_input;
                 if !matches!(device_input.input_type, InputType::Other) {
+                    let value = match input_element.hat_switch_range {
+                        Some(range) => range.normalize(input_event.value),
+                        None => input_event.value,
+                    };
                     return Some(InputEvent {
                         device_type: self.device_type,
-                        device_input: *device_input,
-                        value: input_event.value,
+                        device_input,
+                        value,
                     });
                 }
             }
@@ -185,8 +197,8 @@ impl HIDDevice {
 unsafe fn build_input_map(
     device: IOHIDDeviceRef,
     device_type: DeviceType,
-) -> HashMap<IOHIDElementCookie, DeviceInput> {
-    let mut input_map = HashMap::<IOHIDElementCookie, DeviceInput>::new();
+) -> HashMap<IOHIDElementCookie, InputElement> {
+    let mut input_map = HashMap::<IOHIDElementCookie, InputElement>::new();
     let mut index_tracker = HashMap::<InputType, i32>::new();
     // Safe because the caller guarantees `device` is valid.
     let elements = unsafe {
@@ -204,7 +216,21 @@ unsafe fn build_input_map(
             if let Some((identifier, device_input)) =
                 DeviceInput::try_new(element, &mut index_tracker)
             {
-                input_map.insert(identifier, device_input);
+                let hat_switch_range = match device_input.input_type {
+                    InputType::Hat => {
+                        let range = HatSwitchRange::from_element(element);
+                        println!("Found {}: {}", device_input, range);
+                        Some(range)
+                    }
+                    _ => None,
+                };
+                input_map.insert(
+                    identifier,
+                    InputElement {
+                        device_input,
+                        hat_switch_range,
+                    },
+                );
             }
         }
     }
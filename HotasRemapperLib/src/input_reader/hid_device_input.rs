@@ -5,8 +5,11 @@ use std::fmt::Result as FmtResult;
 
 use io_kit_sys::hid::base::IOHIDElementRef;
 use io_kit_sys::hid::element::IOHIDElementGetCookie;
+use io_kit_sys::hid::element::IOHIDElementGetLogicalMax;
+use io_kit_sys::hid::element::IOHIDElementGetLogicalMin;
 use io_kit_sys::hid::element::IOHIDElementGetType;
 use io_kit_sys::hid::element::IOHIDElementGetUsage;
+use io_kit_sys::hid::element::IOHIDElementHasNullState;
 use io_kit_sys::hid::keys::kIOHIDElementTypeCollection;
 use io_kit_sys::hid::keys::kIOHIDElementTypeInput_Button;
 use io_kit_sys::hid::keys::kIOHIDElementTypeInput_Misc;
@@ -27,6 +30,12 @@ const kIOHIDElementTypeInput_NULL: IOHIDElementType = 5;
 #[allow(non_upper_case_globals)]
 const kHIDUsage_Ignored: u32 = kHIDUsage_GD_X - 1;
 
+/// Hat switch values are normalized to positions in the range of [0, 7], where
+/// 0 is up and each step is 45 degrees clockwise, regardless of how many
+/// positions the hat switch actually has. The center is reported as this value.
+pub(crate) const HAT_SWITCH_CENTER: i32 = -1;
+const NUM_HAT_SWITCH_POSITIONS: i32 = 8;
+
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 pub(crate) enum InputType {
     Button,
@@ -127,3 +136,79 @@ impl Display for DeviceInput {
         formatter.write_fmt(format_args!("{:?}{}", self.input_type, self.index))
     }
 }
+
+/// Hat switches from different vendors don't agree on the values they report.
+/// For example, the first position may be 0 or 1, and the center may be
+/// reported as a null value (e.g. 15 or -1) or as an extra value within the
+/// logical range (e.g. 8). This struct uses the properties of the element to
+/// normalize them.
+#[derive(Clone, Copy)]
+pub(crate) struct HatSwitchRange {
+    logical_min: i32,
+    logical_max: i32,
+    has_null_state: bool,
+}
+
+impl HatSwitchRange {
+    /// Safety: the caller must ensure the element is alive.
+    #[deny(unsafe_op_in_unsafe_fn)]
+    pub unsafe fn from_element(element: IOHIDElementRef) -> Self {
+        // Safe because the caller guarantees `element` is valid.
+        unsafe {
+            Self {
+                logical_min: IOHIDElementGetLogicalMin(element) as i32,
+                logical_max: IOHIDElementGetLogicalMax(element) as i32,
+                has_null_state: IOHIDElementHasNullState(element) != 0,
+            }
+        }
+    }
+
+    /// Returns the position as described in `HAT_SWITCH_CENTER`.
+    pub fn normalize(&self, value: i32) -> i32 {
+        if value < self.logical_min || value > self.logical_max {
+            return HAT_SWITCH_CENTER;
+        }
+        // Widened, since devices may have logical ranges that don't fit in i32
+        // once multiplied.
+        let mut num_positions =
+            self.logical_max as i64 - self.logical_min as i64 + 1;
+        // Without a null state, a 4-way or 8-way hat switch needs an extra
+        // value for the center, which is conventionally the last one.
+        if !self.has_null_state && num_positions % 2 == 1 {
+            if value == self.logical_max {
+                return HAT_SWITCH_CENTER;
+            }
+            num_positions -= 1;
+        }
+        ((value as i64 - self.logical_min as i64)
+            * NUM_HAT_SWITCH_POSITIONS as i64
+            / num_positions) as i32
+    }
+}
+
+impl Display for HatSwitchRange {
+    fn fmt(&self, formatter: &mut Formatter) -> FmtResult {
+        formatter.write_fmt(format_args!(
+            "{{logical range: [{}, {}], has null state: {}}}",
+            self.logical_min, self.logical_max, self.has_null_state
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_hat_switch_with_large_logical_range() {
+        let range = HatSwitchRange {
+            logical_min: i32::MIN,
+            logical_max: i32::MAX,
+            has_null_state: true,
+        };
+
+        assert_eq!(range.normalize(i32::MIN), 0);
+        assert_eq!(range.normalize(0), NUM_HAT_SWITCH_POSITIONS / 2);
+        assert_eq!(range.normalize(i32::MAX), NUM_HAT_SWITCH_POSITIONS - 1);
+    }
+}
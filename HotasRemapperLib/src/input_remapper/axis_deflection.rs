@@ -0,0 +1,62 @@
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+
+use anyhow::bail;
+use anyhow::Result;
+
+/// Converts axis values to the deflection from the center of the axis, which
+/// is in the range of [-1.0, 1.0]. Values within the deadzone are converted to
+/// 0.0, and the rest is rescaled so that the deflection grows continuously from
+/// the edge of the deadzone.
+pub(crate) struct AxisDeflection {
+    center: f64,
+    // Negative if the axis is reversed.
+    half_range: f64,
+    deadzone: f64,
+}
+
+impl AxisDeflection {
+    pub fn new(
+        min_value: i32,
+        max_value: i32,
+        reverse_axis: bool,
+        deadzone: f32,
+    ) -> Result<Self> {
+        if min_value >= max_value {
+            bail!("min_value must be less than max_value!");
+        }
+        if !(0.0..1.0).contains(&deadzone) {
+            bail!("deadzone must be in [0, 1)!");
+        }
+        let half_range = (max_value as f64 - min_value as f64) / 2.0;
+        Ok(Self {
+            center: (min_value as f64 + max_value as f64) / 2.0,
+            half_range: if !reverse_axis {
+                half_range
+            } else {
+                -half_range
+            },
+            deadzone: deadzone as f64,
+        })
+    }
+
+    pub fn deflection(&self, value: i32) -> f64 {
+        let deflection =
+            ((value as f64 - self.center) / self.half_range).clamp(-1.0, 1.0);
+        if deflection.abs() <= self.deadzone {
+            return 0.0;
+        }
+        deflection.signum() * (deflection.abs() - self.deadzone)
+            / (1.0 - self.deadzone)
+    }
+}
+
+impl Display for AxisDeflection {
+    fn fmt(&self, formatter: &mut Formatter) -> FmtResult {
+        formatter.write_fmt(format_args!(
+            "{{center: {}, half range: {}, deadzone: {}}}",
+            self.center, self.half_range, self.deadzone
+        ))
+    }
+}
@@ -0,0 +1,149 @@
+use std::cell::RefCell;
+use std::convert::TryFrom;
+use std::ffi::c_char;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::time::Duration;
+use std::time::Instant;
+
+use anyhow::bail;
+
+use super::axis_deflection::AxisDeflection;
+use super::convert_key_codes;
+use super::RemapInputValue;
+use crate::input_remapping::AxisInput;
+use crate::virtual_device::KeyEvent;
+
+struct KeyCycle {
+    key_code: c_char,
+    duty_cycle: f64,
+    period_start_time: Instant,
+    is_pressed: bool,
+}
+
+pub(crate) struct AxisDutyCycleRemapper {
+    negative_key_code: c_char,
+    positive_key_code: c_char,
+    axis_deflection: AxisDeflection,
+    period: Duration,
+    key_cycle: RefCell<Option<KeyCycle>>,
+}
+
+impl TryFrom<&AxisInput> for AxisDutyCycleRemapper {
+    type Error = anyhow::Error;
+
+    fn try_from(input: &AxisInput) -> Result<Self, Self::Error> {
+        let mode = input.duty_cycle_mode();
+        let key_codes = convert_key_codes(&[
+            mode.negative_key_code,
+            mode.positive_key_code,
+        ])?;
+        if mode.period_ms <= 0 {
+            bail!("period_ms must be positive!");
+        }
+        Ok(Self {
+            negative_key_code: key_codes[0],
+            positive_key_code: key_codes[1],
+            axis_deflection: AxisDeflection::new(
+                input.min_value,
+                input.max_value,
+                input.reverse_axis,
+                mode.deadzone,
+            )?,
+            period: Duration::from_millis(mode.period_ms as u64),
+            key_cycle: RefCell::new(None),
+        })
+    }
+}
+
+impl RemapInputValue for AxisDutyCycleRemapper {
+    fn remap(&self, value: i32) -> Vec<KeyEvent> {
+        let deflection = self.axis_deflection.deflection(value);
+        if deflection == 0.0 {
+            return match self.key_cycle.take() {
+                Some(key_cycle) if key_cycle.is_pressed => {
+                    vec![KeyEvent::Release(key_cycle.key_code)]
+                }
+                _ => vec![],
+            };
+        }
+
+        let key_code = if deflection < 0.0 {
+            self.negative_key_code
+        } else {
+            self.positive_key_code
+        };
+        let mut key_cycle = self.key_cycle.borrow_mut();
+        // If the direction is unchanged, the new duty cycle will take effect
+        // when `tick()` decides whether to release the key.
+        if let Some(key_cycle) = key_cycle.as_mut() {
+            if key_cycle.key_code == key_code {
+                key_cycle.duty_cycle = deflection.abs();
+                return vec![];
+            }
+        }
+        // Otherwise, start a new period with the new key pressed. Make sure we
+        // never hold both keys, since they would compete for the same slot in
+        // the input report and cancel each other out in the game.
+        let key_to_release = key_cycle
+            .as_ref()
+            .filter(|key_cycle| key_cycle.is_pressed)
+            .map(|key_cycle| key_cycle.key_code);
+        key_cycle.replace(KeyCycle {
+            key_code,
+            duty_cycle: deflection.abs(),
+            period_start_time: Instant::now(),
+            is_pressed: true,
+        });
+        vec![match key_to_release {
+            Some(to_release) => KeyEvent::ReleaseAndPress {
+                to_release,
+                to_press: key_code,
+            },
+            None => KeyEvent::Press(key_code),
+        }]
+    }
+
+    fn tick(&self, now: Instant) -> Vec<KeyEvent> {
+        let mut key_cycle = self.key_cycle.borrow_mut();
+        let Some(key_cycle) = key_cycle.as_mut() else {
+            return vec![];
+        };
+        if now - key_cycle.period_start_time >= self.period {
+            // Don't try to catch up if the timer fired late.
+            key_cycle.period_start_time =
+                if now - key_cycle.period_start_time < self.period * 2 {
+                    key_cycle.period_start_time + self.period
+                } else {
+                    now
+                };
+            if !key_cycle.is_pressed {
+                key_cycle.is_pressed = true;
+                return vec![KeyEvent::Press(key_cycle.key_code)];
+            }
+        }
+        // Keep holding the key if the axis is fully deflected.
+        let hold_duration = self.period.mul_f64(key_cycle.duty_cycle);
+        if key_cycle.is_pressed
+            && hold_duration < self.period
+            && now - key_cycle.period_start_time >= hold_duration
+        {
+            key_cycle.is_pressed = false;
+            return vec![KeyEvent::Release(key_cycle.key_code)];
+        }
+        vec![]
+    }
+}
+
+impl Display for AxisDutyCycleRemapper {
+    fn fmt(&self, formatter: &mut Formatter) -> FmtResult {
+        formatter.write_fmt(format_args!(
+            "{{negative: {}, positive: {}, axis: {}, period: {:?}}}",
+            self.negative_key_code,
+            self.positive_key_code,
+            self.axis_deflection,
+            self.period,
+        ))
+    }
+}
@@ -35,12 +35,12 @@ impl TryFrom<&AxisInput> for AxisRemapper {
 }
 
 impl RemapInputValue for AxisRemapper {
-    fn remap(&self, value: i32) -> Option<KeyEvent> {
+    fn remap(&self, value: i32) -> Vec<KeyEvent> {
         let index_f64 = (value as f64 - self.min_value) / self.interval;
         let index =
             (index_f64.round() as usize).clamp(0, self.key_codes.len() - 1);
         let key_code = self.key_codes[index];
-        Some(KeyEvent::PressAndRelease(key_code))
+        vec![KeyEvent::PressAndRelease(key_code)]
     }
 }
 
@@ -0,0 +1,148 @@
+use std::cell::RefCell;
+use std::convert::TryFrom;
+use std::ffi::c_char;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::time::Duration;
+use std::time::Instant;
+
+use anyhow::bail;
+
+use super::axis_deflection::AxisDeflection;
+use super::convert_key_codes;
+use super::RemapInputValue;
+use crate::input_remapping::AxisInput;
+use crate::virtual_device::KeyEvent;
+
+struct RepeatingKey {
+    key_code: c_char,
+    interval: Duration,
+    last_tap_time: Instant,
+}
+
+pub(crate) struct AxisRepeatRateRemapper {
+    negative_key_code: c_char,
+    positive_key_code: c_char,
+    axis_deflection: AxisDeflection,
+    min_repeat_hz: f64,
+    max_repeat_hz: f64,
+    curve_exponent: f64,
+    repeating_key: RefCell<Option<RepeatingKey>>,
+}
+
+impl TryFrom<&AxisInput> for AxisRepeatRateRemapper {
+    type Error = anyhow::Error;
+
+    fn try_from(input: &AxisInput) -> Result<Self, Self::Error> {
+        let mode = input.repeat_rate_mode();
+        let key_codes = convert_key_codes(&[
+            mode.negative_key_code,
+            mode.positive_key_code,
+        ])?;
+        if mode.min_repeat_hz <= 0.0 {
+            bail!("min_repeat_hz must be positive!");
+        }
+        if mode.max_repeat_hz < mode.min_repeat_hz {
+            bail!("max_repeat_hz must not be less than min_repeat_hz!");
+        }
+        if mode.curve_exponent < 0.0 {
+            bail!("curve_exponent must be non-negative!");
+        }
+        Ok(Self {
+            negative_key_code: key_codes[0],
+            positive_key_code: key_codes[1],
+            axis_deflection: AxisDeflection::new(
+                input.min_value,
+                input.max_value,
+                input.reverse_axis,
+                mode.deadzone,
+            )?,
+            min_repeat_hz: mode.min_repeat_hz as f64,
+            max_repeat_hz: mode.max_repeat_hz as f64,
+            curve_exponent: if mode.curve_exponent == 0.0 {
+                1.0
+            } else {
+                mode.curve_exponent as f64
+            },
+            repeating_key: RefCell::new(None),
+        })
+    }
+}
+
+impl AxisRepeatRateRemapper {
+    fn get_repeat_interval(&self, deflection: f64) -> Duration {
+        let repeat_hz = self.min_repeat_hz
+            + (self.max_repeat_hz - self.min_repeat_hz)
+                * deflection.abs().powf(self.curve_exponent);
+        Duration::from_secs_f64(1.0 / repeat_hz)
+    }
+}
+
+impl RemapInputValue for AxisRepeatRateRemapper {
+    fn remap(&self, value: i32) -> Vec<KeyEvent> {
+        let deflection = self.axis_deflection.deflection(value);
+        if deflection == 0.0 {
+            self.repeating_key.replace(None);
+            return vec![];
+        }
+
+        let key_code = if deflection < 0.0 {
+            self.negative_key_code
+        } else {
+            self.positive_key_code
+        };
+        let interval = self.get_repeat_interval(deflection);
+        let mut repeating_key = self.repeating_key.borrow_mut();
+        // If we are already tapping the same key, only update the interval and
+        // let `tick()` take care of the timing, so that moving the axis doesn't
+        // result in extra taps.
+        if let Some(repeating_key) = repeating_key.as_mut() {
+            if repeating_key.key_code == key_code {
+                repeating_key.interval = interval;
+                return vec![];
+            }
+        }
+        repeating_key.replace(RepeatingKey {
+            key_code,
+            interval,
+            last_tap_time: Instant::now(),
+        });
+        vec![KeyEvent::PressAndRelease(key_code)]
+    }
+
+    fn tick(&self, now: Instant) -> Vec<KeyEvent> {
+        let mut repeating_key = self.repeating_key.borrow_mut();
+        let Some(repeating_key) = repeating_key.as_mut() else {
+            return vec![];
+        };
+        let next_tap_time =
+            repeating_key.last_tap_time + repeating_key.interval;
+        if now < next_tap_time {
+            return vec![];
+        }
+        // Don't try to catch up if the timer fired late.
+        repeating_key.last_tap_time =
+            if now - next_tap_time < repeating_key.interval {
+                next_tap_time
+            } else {
+                now
+            };
+        vec![KeyEvent::PressAndRelease(repeating_key.key_code)]
+    }
+}
+
+impl Display for AxisRepeatRateRemapper {
+    fn fmt(&self, formatter: &mut Formatter) -> FmtResult {
+        formatter.write_fmt(format_args!(
+            "{{negative: {}, positive: {}, axis: {}, repeat rate: {}-{} Hz, \
+            curve exponent: {}}}",
+            self.negative_key_code,
+            self.positive_key_code,
+            self.axis_deflection,
+            self.min_repeat_hz,
+            self.max_repeat_hz,
+            self.curve_exponent,
+        ))
+    }
+}
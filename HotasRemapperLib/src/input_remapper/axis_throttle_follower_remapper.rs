@@ -0,0 +1,222 @@
+use std::cell::RefCell;
+use std::convert::TryFrom;
+use std::ffi::c_char;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::time::Duration;
+use std::time::Instant;
+
+use anyhow::bail;
+
+use super::axis_deflection::AxisDeflection;
+use super::convert_key_codes;
+use super::RemapInputValue;
+use crate::input_remapping::AxisInput;
+use crate::virtual_device::KeyEvent;
+
+#[derive(Clone, Copy, Eq, PartialEq)]
+enum Direction {
+    Decrease,
+    Increase,
+}
+
+#[derive(Clone, Copy, Eq, PartialEq)]
+enum Endpoint {
+    Min,
+    Max,
+}
+
+struct FollowerState {
+    // Both positions are in [0.0, 1.0]. `target_position` is None until we
+    // receive the first input event.
+    target_position: Option<f64>,
+    model_position: f64,
+    held_direction: Option<Direction>,
+    last_update_time: Instant,
+    synced_endpoint: Option<Endpoint>,
+}
+
+pub(crate) struct AxisThrottleFollowerRemapper {
+    decrease_key_code: c_char,
+    increase_key_code: c_char,
+    decrease_full_range: Duration,
+    increase_full_range: Duration,
+    tolerance: f64,
+    // 0 if not specified.
+    min_key_code: c_char,
+    max_key_code: c_char,
+    axis_deflection: AxisDeflection,
+    state: RefCell<FollowerState>,
+}
+
+impl TryFrom<&AxisInput> for AxisThrottleFollowerRemapper {
+    type Error = anyhow::Error;
+
+    fn try_from(input: &AxisInput) -> Result<Self, Self::Error> {
+        let mode = input.throttle_follower_mode();
+        let key_codes = convert_key_codes(&[
+            mode.decrease_key_code,
+            mode.increase_key_code,
+            mode.min_key_code,
+            mode.max_key_code,
+        ])?;
+        if mode.decrease_full_range_ms <= 0 || mode.increase_full_range_ms <= 0
+        {
+            bail!("decrease/increase_full_range_ms must be positive!");
+        }
+        if !(mode.tolerance > 0.0 && mode.tolerance < 0.5) {
+            bail!("tolerance must be in (0, 0.5)!");
+        }
+        Ok(Self {
+            decrease_key_code: key_codes[0],
+            increase_key_code: key_codes[1],
+            decrease_full_range: Duration::from_millis(
+                mode.decrease_full_range_ms as u64,
+            ),
+            increase_full_range: Duration::from_millis(
+                mode.increase_full_range_ms as u64,
+            ),
+            tolerance: mode.tolerance as f64,
+            min_key_code: key_codes[2],
+            max_key_code: key_codes[3],
+            axis_deflection: AxisDeflection::new(
+                input.min_value,
+                input.max_value,
+                input.reverse_axis,
+                /* deadzone= */ 0.0,
+            )?,
+            state: RefCell::new(FollowerState {
+                target_position: None,
+                model_position: 0.0,
+                held_direction: None,
+                last_update_time: Instant::now(),
+                synced_endpoint: None,
+            }),
+        })
+    }
+}
+
+impl AxisThrottleFollowerRemapper {
+    fn get_key_code(&self, direction: Direction) -> c_char {
+        match direction {
+            Direction::Decrease => self.decrease_key_code,
+            Direction::Increase => self.increase_key_code,
+        }
+    }
+
+    fn get_endpoint(&self, position: f64) -> Option<Endpoint> {
+        if position <= self.tolerance {
+            Some(Endpoint::Min)
+        } else if position >= 1.0 - self.tolerance {
+            Some(Endpoint::Max)
+        } else {
+            None
+        }
+    }
+
+    /// Moves the model according to the key that has been held since the last
+    /// update.
+    fn update_model_position(&self, state: &mut FollowerState, now: Instant) {
+        let elapsed_time = now - state.last_update_time;
+        state.last_update_time = now;
+        let delta = match state.held_direction {
+            Some(Direction::Decrease) => {
+                -elapsed_time.as_secs_f64()
+                    / self.decrease_full_range.as_secs_f64()
+            }
+            Some(Direction::Increase) => {
+                elapsed_time.as_secs_f64()
+                    / self.increase_full_range.as_secs_f64()
+            }
+            None => return,
+        };
+        state.model_position = (state.model_position + delta).clamp(0.0, 1.0);
+    }
+}
+
+impl RemapInputValue for AxisThrottleFollowerRemapper {
+    fn remap(&self, value: i32) -> Vec<KeyEvent> {
+        // Key events are only fired by `tick()`, so that the model is always
+        // updated at a steady pace.
+        let position = (self.axis_deflection.deflection(value) + 1.0) / 2.0;
+        self.state.borrow_mut().target_position = Some(position);
+        vec![]
+    }
+
+    fn tick(&self, now: Instant) -> Vec<KeyEvent> {
+        let mut state = self.state.borrow_mut();
+        self.update_model_position(&mut state, now);
+        let Some(target_position) = state.target_position else {
+            return vec![];
+        };
+
+        // Resync the model if the axis has just moved to either end. If any key
+        // is held, release it first.
+        let endpoint = self.get_endpoint(target_position);
+        if endpoint != state.synced_endpoint {
+            let snap_key_code = match endpoint {
+                Some(Endpoint::Min) => self.min_key_code,
+                Some(Endpoint::Max) => self.max_key_code,
+                None => 0,
+            };
+            if snap_key_code != 0 {
+                let mut key_events = vec![];
+                if let Some(direction) = state.held_direction.take() {
+                    key_events
+                        .push(KeyEvent::Release(self.get_key_code(direction)));
+                }
+                key_events.push(KeyEvent::PressAndRelease(snap_key_code));
+                state.synced_endpoint = endpoint;
+                state.model_position = match endpoint {
+                    Some(Endpoint::Max) => 1.0,
+                    _ => 0.0,
+                };
+                return key_events;
+            }
+            state.synced_endpoint = None;
+        }
+
+        // Only start moving if the model deviates by more than the tolerance,
+        // but keep moving until the model reaches the axis, so that we don't
+        // keep toggling keys around the boundary.
+        let position_diff = target_position - state.model_position;
+        match state.held_direction {
+            Some(Direction::Decrease) if position_diff >= 0.0 => {
+                state.held_direction = None;
+                vec![KeyEvent::Release(self.decrease_key_code)]
+            }
+            Some(Direction::Increase) if position_diff <= 0.0 => {
+                state.held_direction = None;
+                vec![KeyEvent::Release(self.increase_key_code)]
+            }
+            Some(_) => vec![],
+            None if position_diff < -self.tolerance => {
+                state.held_direction = Some(Direction::Decrease);
+                vec![KeyEvent::Press(self.decrease_key_code)]
+            }
+            None if position_diff > self.tolerance => {
+                state.held_direction = Some(Direction::Increase);
+                vec![KeyEvent::Press(self.increase_key_code)]
+            }
+            None => vec![],
+        }
+    }
+}
+
+impl Display for AxisThrottleFollowerRemapper {
+    fn fmt(&self, formatter: &mut Formatter) -> FmtResult {
+        formatter.write_fmt(format_args!(
+            "{{decrease: {} ({:?}), increase: {} ({:?}), tolerance: {}, \
+            min: {}, max: {}, axis: {}}}",
+            self.decrease_key_code,
+            self.decrease_full_range,
+            self.increase_key_code,
+            self.increase_full_range,
+            self.tolerance,
+            self.min_key_code,
+            self.max_key_code,
+            self.axis_deflection,
+        ))
+    }
+}
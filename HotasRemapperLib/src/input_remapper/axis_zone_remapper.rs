@@ -0,0 +1,148 @@
+use std::cell::RefCell;
+use std::convert::TryFrom;
+use std::ffi::c_char;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+
+use anyhow::bail;
+
+use super::convert_key_codes;
+use super::RemapInputValue;
+use crate::input_remapping::AxisZone as AxisZoneProto;
+use crate::input_remapping::AxisZoneInput;
+use crate::virtual_device::KeyEvent;
+
+struct AxisZone {
+    min_value: i32,
+    max_value: i32,
+    // 0 if not specified.
+    enter_key_code: c_char,
+    exit_key_code: c_char,
+    hold_key_code: c_char,
+}
+
+impl TryFrom<&AxisZoneProto> for AxisZone {
+    type Error = anyhow::Error;
+
+    fn try_from(zone: &AxisZoneProto) -> Result<Self, Self::Error> {
+        if zone.min_value > zone.max_value {
+            bail!("min_value must not be greater than max_value!");
+        }
+        let key_codes = convert_key_codes(&[
+            zone.enter_key_code,
+            zone.exit_key_code,
+            zone.hold_key_code,
+        ])?;
+        Ok(Self {
+            min_value: zone.min_value,
+            max_value: zone.max_value,
+            enter_key_code: key_codes[0],
+            exit_key_code: key_codes[1],
+            hold_key_code: key_codes[2],
+        })
+    }
+}
+
+impl AxisZone {
+    fn contains(&self, value: i32, margin: i32) -> bool {
+        (self.min_value.saturating_sub(margin)
+            ..=self.max_value.saturating_add(margin))
+            .contains(&value)
+    }
+}
+
+impl Display for AxisZone {
+    fn fmt(&self, formatter: &mut Formatter) -> FmtResult {
+        formatter.write_fmt(format_args!(
+            "{{[{}, {}], enter: {}, exit: {}, hold: {}}}",
+            self.min_value,
+            self.max_value,
+            self.enter_key_code,
+            self.exit_key_code,
+            self.hold_key_code,
+        ))
+    }
+}
+
+pub(crate) struct AxisZoneRemapper {
+    zones: Vec<AxisZone>,
+    hysteresis: i32,
+    is_within_zones: RefCell<Vec<bool>>,
+}
+
+impl TryFrom<&AxisZoneInput> for AxisZoneRemapper {
+    type Error = anyhow::Error;
+
+    fn try_from(input: &AxisZoneInput) -> Result<Self, Self::Error> {
+        if input.zones.is_empty() {
+            bail!("No zones provided!");
+        }
+        if input.hysteresis < 0 {
+            bail!("hysteresis must be non-negative!");
+        }
+        let zones = input
+            .zones
+            .iter()
+            .map(AxisZone::try_from)
+            .collect::<Result<Vec<_>, _>>()?;
+        let num_zones = zones.len();
+        Ok(Self {
+            zones,
+            hysteresis: input.hysteresis,
+            is_within_zones: RefCell::new(vec![false; num_zones]),
+        })
+    }
+}
+
+impl RemapInputValue for AxisZoneRemapper {
+    fn remap(&self, value: i32) -> Vec<KeyEvent> {
+        let mut exit_events = vec![];
+        let mut enter_events = vec![];
+        for (zone, is_within_zone) in self
+            .zones
+            .iter()
+            .zip(self.is_within_zones.borrow_mut().iter_mut())
+        {
+            let margin = if *is_within_zone { self.hysteresis } else { 0 };
+            let is_now_within_zone = zone.contains(value, margin);
+            if is_now_within_zone == *is_within_zone {
+                continue;
+            }
+            *is_within_zone = is_now_within_zone;
+            if is_now_within_zone {
+                if zone.enter_key_code != 0 {
+                    enter_events
+                        .push(KeyEvent::PressAndRelease(zone.enter_key_code));
+                }
+                if zone.hold_key_code != 0 {
+                    enter_events.push(KeyEvent::Press(zone.hold_key_code));
+                }
+            } else {
+                if zone.hold_key_code != 0 {
+                    exit_events.push(KeyEvent::Release(zone.hold_key_code));
+                }
+                if zone.exit_key_code != 0 {
+                    exit_events
+                        .push(KeyEvent::PressAndRelease(zone.exit_key_code));
+                }
+            }
+        }
+        exit_events.append(&mut enter_events);
+        exit_events
+    }
+}
+
+impl Display for AxisZoneRemapper {
+    fn fmt(&self, formatter: &mut Formatter) -> FmtResult {
+        formatter.write_str("{zones: [")?;
+        for (index, zone) in self.zones.iter().enumerate() {
+            if index > 0 {
+                formatter.write_str(", ")?;
+            }
+            zone.fmt(formatter)?;
+        }
+        formatter
+            .write_fmt(format_args!("], hysteresis: {}}}", self.hysteresis))
+    }
+}
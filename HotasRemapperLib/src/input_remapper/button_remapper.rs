@@ -3,36 +3,68 @@ use std::ffi::c_char;
 use std::fmt::Display;
 use std::fmt::Formatter;
 use std::fmt::Result as FmtResult;
+use std::time::Instant;
 
 use super::convert_key_code;
+use super::key_repeater::KeyRepeater;
 use super::RemapInputValue;
 use crate::input_remapping::ButtonInput;
 use crate::virtual_device::KeyEvent;
 
 pub(crate) struct ButtonRemapper {
     key_code: c_char,
+    key_repeater: Option<KeyRepeater>,
 }
 
 impl TryFrom<&ButtonInput> for ButtonRemapper {
     type Error = anyhow::Error;
 
     fn try_from(input: &ButtonInput) -> Result<Self, Self::Error> {
-        convert_key_code(input.key_code).map(|key_code| Self { key_code })
+        let key_code = convert_key_code(input.key_code)?;
+        let key_repeater = input
+            .auto_repeat
+            .as_ref()
+            .map(KeyRepeater::try_from)
+            .transpose()?;
+        Ok(Self {
+            key_code,
+            key_repeater,
+        })
     }
 }
 
 impl RemapInputValue for ButtonRemapper {
-    fn remap(&self, value: i32) -> Option<KeyEvent> {
-        Some(if value != 0 {
+    fn remap(&self, value: i32) -> Vec<KeyEvent> {
+        if let Some(key_repeater) = self.key_repeater.as_ref() {
+            if value != 0 {
+                return key_repeater.start(&[self.key_code]);
+            }
+            key_repeater.stop();
+            return vec![];
+        }
+        vec![if value != 0 {
             KeyEvent::Press(self.key_code)
         } else {
             KeyEvent::Release(self.key_code)
-        })
+        }]
+    }
+
+    fn tick(&self, now: Instant) -> Vec<KeyEvent> {
+        self.key_repeater
+            .as_ref()
+            .map(|key_repeater| key_repeater.tick(now))
+            .unwrap_or_default()
     }
 }
 
 impl Display for ButtonRemapper {
     fn fmt(&self, formatter: &mut Formatter) -> FmtResult {
-        formatter.write_fmt(format_args!("{}", self.key_code))
+        match self.key_repeater.as_ref() {
+            Some(key_repeater) => formatter.write_fmt(format_args!(
+                "{} (auto repeat: {})",
+                self.key_code, key_repeater
+            )),
+            None => formatter.write_fmt(format_args!("{}", self.key_code)),
+        }
     }
 }
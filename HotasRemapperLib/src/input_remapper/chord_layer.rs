@@ -0,0 +1,209 @@
+use std::cell::Cell;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::convert::TryFrom;
+use std::ffi::c_char;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+
+use anyhow::bail;
+use anyhow::Result;
+
+use super::convert_key_code;
+use super::format_input_identifiers;
+use super::InputIdentifier;
+use crate::input_reader::hid_device_input::InputType;
+use crate::input_remapping::ButtonChord;
+use crate::virtual_device::KeyEvent;
+use crate::virtual_device::KeySource;
+
+struct Chord {
+    inputs: Vec<InputIdentifier>,
+    source: KeySource,
+    key_code: c_char,
+    is_order_sensitive: bool,
+    suppresses_inputs: bool,
+    // The order in which each input has been pressed, or None if released.
+    press_orders: RefCell<Vec<Option<u64>>>,
+    is_active: Cell<bool>,
+}
+
+impl TryFrom<&ButtonChord> for Chord {
+    type Error = anyhow::Error;
+
+    fn try_from(chord: &ButtonChord) -> Result<Self, Self::Error> {
+        if chord.inputs.len() < 2 {
+            bail!("A chord needs at least 2 inputs!");
+        }
+        let inputs = chord
+            .inputs
+            .iter()
+            .map(InputIdentifier::try_from)
+            .collect::<Result<Vec<_>>>()?;
+        for input in inputs.iter() {
+            if input.device_input.input_type != InputType::Button {
+                bail!("{} is not a button", input);
+            }
+        }
+        let num_inputs = inputs.len();
+        let source = KeySource::new(format!(
+            "chord {}",
+            format_input_identifiers(&inputs)
+        ));
+        Ok(Self {
+            inputs,
+            source,
+            key_code: convert_key_code(chord.key_code)?,
+            is_order_sensitive: chord.is_order_sensitive,
+            suppresses_inputs: chord.suppresses_inputs,
+            press_orders: RefCell::new(vec![None; num_inputs]),
+            is_active: Cell::new(false),
+        })
+    }
+}
+
+impl Chord {
+    /// Returns whether the chord is active if it has changed.
+    fn update(
+        &self,
+        input_index: usize,
+        press_order: Option<u64>,
+    ) -> Option<bool> {
+        let mut press_orders = self.press_orders.borrow_mut();
+        if press_orders[input_index].is_some() == press_order.is_some() {
+            return None;
+        }
+        press_orders[input_index] = press_order;
+        let is_active = press_orders.iter().all(Option::is_some)
+            && (!self.is_order_sensitive
+                || press_orders.windows(2).all(|orders| orders[0] < orders[1]));
+        if self.is_active.replace(is_active) == is_active {
+            return None;
+        }
+        Some(is_active)
+    }
+}
+
+impl Display for Chord {
+    fn fmt(&self, formatter: &mut Formatter) -> FmtResult {
+        formatter.write_fmt(format_args!(
+            "{{key code: {}, order sensitive: {}, suppresses inputs: {}}}",
+            self.key_code, self.is_order_sensitive, self.suppresses_inputs
+        ))
+    }
+}
+
+/// What to do with an input event after it has gone through the chord layer.
+#[derive(Default)]
+pub(crate) struct ChordLayerOutput {
+    /// Inputs whose own remappings should see a release, since they are
+    /// suppressed by a chord from now on.
+    pub inputs_to_release: Vec<InputIdentifier>,
+    pub key_events: Vec<(KeySource, KeyEvent)>,
+    /// Whether the input's own remapping should see the event.
+    pub should_forward: bool,
+}
+
+/// Tracks the buttons across devices to detect chords. It sits in front of
+/// the remappers of individual inputs, so that it can suppress them.
+pub(crate) struct ChordLayer {
+    chords: Vec<Chord>,
+    // Maps each input to the indices of the chords it belongs to and its index
+    // within each chord.
+    chord_indices: HashMap<InputIdentifier, Vec<(usize, usize)>>,
+    suppressed_inputs: RefCell<HashSet<InputIdentifier>>,
+    next_press_order: Cell<u64>,
+}
+
+impl ChordLayer {
+    pub fn new() -> Self {
+        Self {
+            chords: Default::default(),
+            chord_indices: Default::default(),
+            suppressed_inputs: Default::default(),
+            next_press_order: Cell::new(0),
+        }
+    }
+
+    pub fn add_chord(&mut self, chord: &ButtonChord) -> Result<()> {
+        let chord = Chord::try_from(chord)?;
+        // Checked before indexing anything, so that the indices never refer to
+        // a chord that isn't added.
+        let mut seen_inputs = HashSet::new();
+        for input in chord.inputs.iter() {
+            if !seen_inputs.insert(input) {
+                bail!("{} appears more than once in a chord", input);
+            }
+        }
+        let chord_index = self.chords.len();
+        for (input_index, input) in chord.inputs.iter().enumerate() {
+            self.chord_indices
+                .entry(*input)
+                .or_default()
+                .push((chord_index, input_index));
+        }
+        println!("\t{} -> {}", chord.source, chord);
+        self.chords.push(chord);
+        Ok(())
+    }
+
+    pub fn remap(
+        &self,
+        input: InputIdentifier,
+        value: i32,
+    ) -> ChordLayerOutput {
+        let Some(chord_indices) = self.chord_indices.get(&input) else {
+            return ChordLayerOutput {
+                should_forward: true,
+                ..Default::default()
+            };
+        };
+        let is_pressed = value != 0;
+        let press_order = is_pressed.then(|| {
+            let press_order = self.next_press_order.get();
+            self.next_press_order.set(press_order + 1);
+            press_order
+        });
+        let mut output = ChordLayerOutput::default();
+        let mut suppressed_inputs = self.suppressed_inputs.borrow_mut();
+        for &(chord_index, input_index) in chord_indices.iter() {
+            let chord = &self.chords[chord_index];
+            match chord.update(input_index, press_order) {
+                Some(true) => {
+                    if chord.suppresses_inputs {
+                        for chord_input in chord.inputs.iter() {
+                            if suppressed_inputs.insert(*chord_input)
+                                && *chord_input != input
+                            {
+                                output.inputs_to_release.push(*chord_input);
+                            }
+                        }
+                    }
+                    if chord.key_code != 0 {
+                        output.key_events.push((
+                            chord.source.clone(),
+                            KeyEvent::Press(chord.key_code),
+                        ));
+                    }
+                }
+                Some(false) if chord.key_code != 0 => {
+                    output.key_events.push((
+                        chord.source.clone(),
+                        KeyEvent::Release(chord.key_code),
+                    ));
+                }
+                _ => {}
+            }
+        }
+        // The remapping of a suppressed input has already seen a release, so
+        // don't forward anything until the input is actually released.
+        output.should_forward = if is_pressed {
+            !suppressed_inputs.contains(&input)
+        } else {
+            !suppressed_inputs.remove(&input)
+        };
+        output
+    }
+}
@@ -0,0 +1,51 @@
+use std::convert::TryFrom;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+
+use anyhow::anyhow;
+
+use super::RemapInputValue;
+use crate::input_remapping::ConsumerControlInput;
+use crate::virtual_device::ConsumerControlEvent;
+use crate::virtual_device::MAX_CONSUMER_CONTROL_USAGE;
+
+pub(crate) struct ConsumerControlRemapper {
+    usage: u16,
+}
+
+impl TryFrom<&ConsumerControlInput> for ConsumerControlRemapper {
+    type Error = anyhow::Error;
+
+    fn try_from(input: &ConsumerControlInput) -> Result<Self, Self::Error> {
+        let usage = u16::try_from(input.usage)
+            .ok()
+            .filter(|usage| (1..=MAX_CONSUMER_CONTROL_USAGE).contains(usage))
+            .ok_or_else(|| {
+                anyhow!(
+                    "usage must be in [1, {:#x}]!",
+                    MAX_CONSUMER_CONTROL_USAGE
+                )
+            })?;
+        Ok(Self { usage })
+    }
+}
+
+impl RemapInputValue<ConsumerControlEvent> for ConsumerControlRemapper {
+    fn remap(&self, value: i32) -> Vec<ConsumerControlEvent> {
+        vec![if value != 0 {
+            ConsumerControlEvent::Press(self.usage)
+        } else {
+            ConsumerControlEvent::Release(self.usage)
+        }]
+    }
+}
+
+impl Display for ConsumerControlRemapper {
+    fn fmt(&self, formatter: &mut Formatter) -> FmtResult {
+        formatter.write_fmt(format_args!(
+            "{{consumer control usage: {:#x}}}",
+            self.usage
+        ))
+    }
+}
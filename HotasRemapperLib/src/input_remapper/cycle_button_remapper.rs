@@ -0,0 +1,55 @@
+use std::cell::Cell;
+use std::convert::TryFrom;
+use std::ffi::c_char;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+
+use anyhow::bail;
+
+use super::convert_key_codes;
+use super::RemapInputValue;
+use crate::input_remapping::CycleButtonInput;
+use crate::virtual_device::KeyEvent;
+
+pub(crate) struct CycleButtonRemapper {
+    key_codes: Vec<c_char>,
+    is_button_pressed: Cell<bool>,
+    next_index: Cell<usize>,
+}
+
+impl TryFrom<&CycleButtonInput> for CycleButtonRemapper {
+    type Error = anyhow::Error;
+
+    fn try_from(input: &CycleButtonInput) -> Result<Self, Self::Error> {
+        if input.key_codes.is_empty() {
+            bail!("No key codes provided!");
+        }
+        Ok(Self {
+            key_codes: convert_key_codes(&input.key_codes)?,
+            is_button_pressed: Cell::new(false),
+            next_index: Cell::new(0),
+        })
+    }
+}
+
+impl RemapInputValue for CycleButtonRemapper {
+    fn remap(&self, value: i32) -> Vec<KeyEvent> {
+        let is_pressed = value != 0;
+        if self.is_button_pressed.replace(is_pressed) || !is_pressed {
+            return vec![];
+        }
+        let index = self.next_index.get();
+        self.next_index.set((index + 1) % self.key_codes.len());
+        match self.key_codes[index] {
+            0 => vec![],
+            key_code => vec![KeyEvent::PressAndRelease(key_code)],
+        }
+    }
+}
+
+impl Display for CycleButtonRemapper {
+    fn fmt(&self, formatter: &mut Formatter) -> FmtResult {
+        formatter.write_fmt(format_args!("cycle {:?}", self.key_codes))
+    }
+}
@@ -0,0 +1,169 @@
+use std::cell::Cell;
+use std::convert::TryFrom;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+
+use anyhow::bail;
+use anyhow::Result;
+
+use super::axis_deflection::AxisDeflection;
+use super::RemapInputValue;
+use crate::input_remapping::gamepad_axis_input::GamepadAxis as GamepadAxisProto;
+use crate::input_remapping::GamepadAxisInput;
+use crate::virtual_device::GamepadAxis;
+use crate::virtual_device::GamepadEvent;
+
+pub(crate) struct GamepadAxisRemapper {
+    gamepad_axis: GamepadAxis,
+    is_trigger: bool,
+    axis_deflection: AxisDeflection,
+    curve_exponent: f64,
+    last_value: Cell<Option<f32>>,
+}
+
+impl TryFrom<&GamepadAxisInput> for GamepadAxisRemapper {
+    type Error = anyhow::Error;
+
+    fn try_from(input: &GamepadAxisInput) -> Result<Self, Self::Error> {
+        let gamepad_axis = match input.gamepad_axis.enum_value() {
+            Ok(GamepadAxisProto::LEFT_STICK_X) => GamepadAxis::LeftStickX,
+            Ok(GamepadAxisProto::LEFT_STICK_Y) => GamepadAxis::LeftStickY,
+            Ok(GamepadAxisProto::RIGHT_STICK_X) => GamepadAxis::RightStickX,
+            Ok(GamepadAxisProto::RIGHT_STICK_Y) => GamepadAxis::RightStickY,
+            Ok(GamepadAxisProto::LEFT_TRIGGER) => GamepadAxis::LeftTrigger,
+            Ok(GamepadAxisProto::RIGHT_TRIGGER) => GamepadAxis::RightTrigger,
+            Err(value) => bail!("Unknown gamepad axis: {}", value),
+        };
+        if input.curve_exponent < 0.0 {
+            bail!("curve_exponent must be non-negative!");
+        }
+        let is_trigger = matches!(
+            gamepad_axis,
+            GamepadAxis::LeftTrigger | GamepadAxis::RightTrigger
+        );
+        // A trigger rests at the minimum value, so the whole value range is
+        // treated as the positive half of an axis centered at the minimum.
+        let (min_value, max_value) = if !is_trigger {
+            (input.min_value, input.max_value)
+        } else if !input.reverse_axis {
+            (mirror(input.max_value, input.min_value)?, input.max_value)
+        } else {
+            (input.min_value, mirror(input.min_value, input.max_value)?)
+        };
+        Ok(Self {
+            gamepad_axis,
+            is_trigger,
+            axis_deflection: AxisDeflection::new(
+                min_value,
+                max_value,
+                input.reverse_axis,
+                input.deadzone,
+            )?,
+            curve_exponent: if input.curve_exponent == 0.0 {
+                1.0
+            } else {
+                input.curve_exponent as f64
+            },
+            last_value: Cell::new(None),
+        })
+    }
+}
+
+impl RemapInputValue<GamepadEvent> for GamepadAxisRemapper {
+    fn remap(&self, value: i32) -> Vec<GamepadEvent> {
+        let mut deflection = self.axis_deflection.deflection(value);
+        if self.is_trigger {
+            deflection = deflection.max(0.0);
+        }
+        let value = (deflection.signum()
+            * deflection.abs().powf(self.curve_exponent))
+            as f32;
+        if self.last_value.replace(Some(value)) == Some(value) {
+            return vec![];
+        }
+        vec![GamepadEvent::MoveAxis {
+            axis: self.gamepad_axis,
+            value,
+        }]
+    }
+}
+
+impl Display for GamepadAxisRemapper {
+    fn fmt(&self, formatter: &mut Formatter) -> FmtResult {
+        formatter.write_fmt(format_args!(
+            "{{gamepad axis: {:?}, axis: {}, curve exponent: {}}}",
+            self.gamepad_axis, self.axis_deflection, self.curve_exponent,
+        ))
+    }
+}
+
+/// Returns the mirror image of `value` around `center`.
+fn mirror(value: i32, center: i32) -> Result<i32> {
+    match center
+        .checked_mul(2)
+        .and_then(|doubled_center| doubled_center.checked_sub(value))
+    {
+        Some(mirrored_value) => Ok(mirrored_value),
+        None => bail!(
+            "The value range of a trigger is too large: [{}, {}]",
+            value.min(center),
+            value.max(center)
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_trigger_input(
+        min_value: i32,
+        max_value: i32,
+        reverse_axis: bool,
+    ) -> GamepadAxisInput {
+        let mut input = GamepadAxisInput::new();
+        input.gamepad_axis = GamepadAxisProto::LEFT_TRIGGER.into();
+        input.min_value = min_value;
+        input.max_value = max_value;
+        input.reverse_axis = reverse_axis;
+        input
+    }
+
+    fn remap_to_value(remapper: &GamepadAxisRemapper, value: i32) -> f32 {
+        match remapper.remap(value)[..] {
+            [GamepadEvent::MoveAxis { value, .. }] => value,
+            _ => panic!("Expected one axis event for {}", value),
+        }
+    }
+
+    #[test]
+    fn trigger_spans_value_range() {
+        for reverse_axis in [false, true] {
+            let remapper = GamepadAxisRemapper::try_from(
+                &create_trigger_input(0, 65535, reverse_axis),
+            )
+            .unwrap();
+            let (rest_value, pressed_value) = if !reverse_axis {
+                (0, 65535)
+            } else {
+                (65535, 0)
+            };
+
+            assert_eq!(remap_to_value(&remapper, rest_value), 0.0);
+            assert_eq!(remap_to_value(&remapper, pressed_value), 1.0);
+        }
+    }
+
+    #[test]
+    fn rejects_trigger_range_that_overflows() {
+        for reverse_axis in [false, true] {
+            assert!(GamepadAxisRemapper::try_from(&create_trigger_input(
+                i32::MIN,
+                i32::MAX,
+                reverse_axis,
+            ))
+            .is_err());
+        }
+    }
+}
@@ -0,0 +1,51 @@
+use std::convert::TryFrom;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+
+use anyhow::anyhow;
+
+use super::RemapInputValue;
+use crate::input_remapping::GamepadButtonInput;
+use crate::virtual_device::GamepadEvent;
+use crate::virtual_device::NUM_GAMEPAD_BUTTONS;
+
+pub(crate) struct GamepadButtonRemapper {
+    gamepad_button: u8,
+}
+
+impl TryFrom<&GamepadButtonInput> for GamepadButtonRemapper {
+    type Error = anyhow::Error;
+
+    fn try_from(input: &GamepadButtonInput) -> Result<Self, Self::Error> {
+        let gamepad_button = u8::try_from(input.gamepad_button)
+            .ok()
+            .filter(|&button| button < NUM_GAMEPAD_BUTTONS)
+            .ok_or_else(|| {
+                anyhow!(
+                    "gamepad_button must be in [0, {})!",
+                    NUM_GAMEPAD_BUTTONS
+                )
+            })?;
+        Ok(Self { gamepad_button })
+    }
+}
+
+impl RemapInputValue<GamepadEvent> for GamepadButtonRemapper {
+    fn remap(&self, value: i32) -> Vec<GamepadEvent> {
+        vec![if value != 0 {
+            GamepadEvent::Press(self.gamepad_button)
+        } else {
+            GamepadEvent::Release(self.gamepad_button)
+        }]
+    }
+}
+
+impl Display for GamepadButtonRemapper {
+    fn fmt(&self, formatter: &mut Formatter) -> FmtResult {
+        formatter.write_fmt(format_args!(
+            "{{gamepad button: {}}}",
+            self.gamepad_button
+        ))
+    }
+}
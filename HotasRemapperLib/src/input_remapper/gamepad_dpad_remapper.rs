@@ -0,0 +1,42 @@
+use std::cell::Cell;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+
+use super::RemapInputValue;
+use crate::input_reader::hid_device_input::HAT_SWITCH_CENTER;
+use crate::virtual_device::GamepadEvent;
+
+pub(crate) struct GamepadDpadRemapper {
+    last_position: Cell<Option<u8>>,
+}
+
+impl GamepadDpadRemapper {
+    pub fn new() -> Self {
+        Self {
+            last_position: Cell::new(None),
+        }
+    }
+}
+
+impl RemapInputValue<GamepadEvent> for GamepadDpadRemapper {
+    /// `value` has been normalized by the input reader (see
+    /// `HAT_SWITCH_CENTER`).
+    fn remap(&self, value: i32) -> Vec<GamepadEvent> {
+        let position = if value == HAT_SWITCH_CENTER {
+            None
+        } else {
+            u8::try_from(value).ok()
+        };
+        if self.last_position.replace(position) == position {
+            return vec![];
+        }
+        vec![GamepadEvent::MoveDpad(position)]
+    }
+}
+
+impl Display for GamepadDpadRemapper {
+    fn fmt(&self, formatter: &mut Formatter) -> FmtResult {
+        formatter.write_str("{gamepad d-pad}")
+    }
+}
@@ -4,17 +4,23 @@ use std::ffi::c_char;
 use std::fmt::Display;
 use std::fmt::Formatter;
 use std::fmt::Result as FmtResult;
+use std::time::Instant;
 
 use anyhow::anyhow;
 
 use super::convert_key_codes;
+use super::key_repeater::KeyRepeater;
 use super::RemapInputValue;
+use crate::input_reader::hid_device_input::HAT_SWITCH_CENTER;
+use crate::input_remapping::hat_switch_input::DiagonalMode;
 use crate::input_remapping::HatSwitchInput;
 use crate::virtual_device::KeyEvent;
 
 pub(crate) struct HatSwitchRemapper {
     key_codes: Vec<c_char>,
-    last_key_code: RefCell<c_char>,
+    diagonal_mode: DiagonalMode,
+    last_key_codes: RefCell<Vec<c_char>>,
+    key_repeater: Option<KeyRepeater>,
 }
 
 impl TryFrom<&HatSwitchInput> for HatSwitchRemapper {
@@ -24,9 +30,16 @@ impl TryFrom<&HatSwitchInput> for HatSwitchRemapper {
         let num_key_codes = input.key_codes.len();
         if num_key_codes == 4 || num_key_codes == 8 {
             let key_codes = convert_key_codes(&input.key_codes)?;
+            let key_repeater = input
+                .auto_repeat
+                .as_ref()
+                .map(KeyRepeater::try_from)
+                .transpose()?;
             Ok(Self {
                 key_codes,
-                last_key_code: RefCell::new(0),
+                diagonal_mode: input.diagonal_mode.enum_value_or_default(),
+                last_key_codes: RefCell::new(vec![]),
+                key_repeater,
             })
         } else {
             Err(anyhow!(
@@ -37,32 +50,121 @@ impl TryFrom<&HatSwitchInput> for HatSwitchRemapper {
     }
 }
 
-impl RemapInputValue for HatSwitchRemapper {
-    fn remap(&self, value: i32) -> Option<KeyEvent> {
-        // An 8-way switch may emit value 15 to signal that the hat has returned
-        // to the center, so we can't always use `value` as the index.
-        let curr_key_code =
-            self.key_codes.get(value as usize).cloned().unwrap_or(0);
-        if curr_key_code == *self.last_key_code.borrow() {
-            return None;
+impl HatSwitchRemapper {
+    /// Returns the keys to hold at the hat position `value`, which has been
+    /// normalized by the input reader (see `HAT_SWITCH_CENTER`).
+    fn get_key_codes(&self, value: i32) -> Vec<c_char> {
+        if value == HAT_SWITCH_CENTER {
+            return vec![];
         }
-        let last_key_code = *self.last_key_code.borrow();
-        self.last_key_code.replace(curr_key_code);
-        Some(if last_key_code == 0 {
-            KeyEvent::Press(curr_key_code)
-        } else if curr_key_code == 0 {
-            KeyEvent::Release(last_key_code)
+        let key_codes = match self.diagonal_mode {
+            // If only 4 keys are provided, diagonal positions of an 8-way hat
+            // switch are not remapped.
+            DiagonalMode::SEPARATE_KEYS => match self.key_codes.len() {
+                4 if value % 2 == 0 => {
+                    vec![self.get_cardinal_key_code(value / 2)]
+                }
+                8 => vec![self
+                    .key_codes
+                    .get(value as usize)
+                    .cloned()
+                    .unwrap_or(0)],
+                _ => vec![],
+            },
+            DiagonalMode::COMBINED_KEYS => match value {
+                // Cardinal positions.
+                0 | 2 | 4 | 6 => vec![self.get_cardinal_key_code(value / 2)],
+                // Diagonal positions.
+                1 | 3 | 5 | 7 => match self.key_codes.get(value as usize) {
+                    Some(&key_code)
+                        if self.key_codes.len() == 8 && key_code != 0 =>
+                    {
+                        vec![key_code]
+                    }
+                    _ => vec![
+                        self.get_cardinal_key_code(value / 2),
+                        self.get_cardinal_key_code((value / 2 + 1) % 4),
+                    ],
+                },
+                _ => vec![],
+            },
+        };
+        key_codes
+            .into_iter()
+            .filter(|&key_code| key_code != 0)
+            .collect()
+    }
+
+    /// `index` is 0 for up, 1 for right, 2 for down and 3 for left.
+    fn get_cardinal_key_code(&self, index: i32) -> c_char {
+        if self.key_codes.len() == 4 {
+            self.key_codes[index as usize]
         } else {
-            KeyEvent::ReleaseAndPress {
-                to_release: last_key_code,
-                to_press: curr_key_code,
+            self.key_codes[index as usize * 2]
+        }
+    }
+}
+
+impl RemapInputValue for HatSwitchRemapper {
+    fn remap(&self, value: i32) -> Vec<KeyEvent> {
+        let curr_key_codes = self.get_key_codes(value);
+        if curr_key_codes == *self.last_key_codes.borrow() {
+            return vec![];
+        }
+        let last_key_codes =
+            self.last_key_codes.replace(curr_key_codes.clone());
+        if let Some(key_repeater) = self.key_repeater.as_ref() {
+            if curr_key_codes.is_empty() {
+                key_repeater.stop();
+                return vec![];
             }
-        })
+            return key_repeater.start(&curr_key_codes);
+        }
+
+        // Release the keys that are no longer held before pressing new ones.
+        let to_release: Vec<c_char> = last_key_codes
+            .iter()
+            .filter(|key_code| !curr_key_codes.contains(key_code))
+            .cloned()
+            .collect();
+        let to_press: Vec<c_char> = curr_key_codes
+            .iter()
+            .filter(|key_code| !last_key_codes.contains(key_code))
+            .cloned()
+            .collect();
+        if let ([to_release], [to_press]) =
+            (to_release.as_slice(), to_press.as_slice())
+        {
+            return vec![KeyEvent::ReleaseAndPress {
+                to_release: *to_release,
+                to_press: *to_press,
+            }];
+        }
+        to_release
+            .into_iter()
+            .map(KeyEvent::Release)
+            .chain(to_press.into_iter().map(KeyEvent::Press))
+            .collect()
+    }
+
+    fn tick(&self, now: Instant) -> Vec<KeyEvent> {
+        self.key_repeater
+            .as_ref()
+            .map(|key_repeater| key_repeater.tick(now))
+            .unwrap_or_default()
     }
 }
 
 impl Display for HatSwitchRemapper {
     fn fmt(&self, formatter: &mut Formatter) -> FmtResult {
-        formatter.write_fmt(format_args!("{:?}", self.key_codes))
+        formatter.write_fmt(format_args!(
+            "{:?} ({:?}",
+            self.key_codes, self.diagonal_mode
+        ))?;
+        if let Some(key_repeater) = self.key_repeater.as_ref() {
+            formatter
+                .write_fmt(format_args!(", auto repeat: {}", key_repeater))?;
+        }
+        formatter.write_str(")")
     }
 }
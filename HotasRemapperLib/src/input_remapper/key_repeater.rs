@@ -0,0 +1,95 @@
+use std::cell::RefCell;
+use std::convert::TryFrom;
+use std::ffi::c_char;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::time::Duration;
+use std::time::Instant;
+
+use anyhow::bail;
+
+use crate::input_remapping::AutoRepeat;
+use crate::virtual_device::KeyEvent;
+
+struct RepeatingKeys {
+    key_codes: Vec<c_char>,
+    next_repeat_time: Instant,
+}
+
+/// Repeatedly taps keys while they are held. The repeating is driven by
+/// `tick()` rather than input events, since the device won't report anything
+/// while the input stays unchanged.
+pub(crate) struct KeyRepeater {
+    initial_delay: Duration,
+    interval: Duration,
+    repeating_keys: RefCell<Option<RepeatingKeys>>,
+}
+
+impl TryFrom<&AutoRepeat> for KeyRepeater {
+    type Error = anyhow::Error;
+
+    fn try_from(auto_repeat: &AutoRepeat) -> Result<Self, Self::Error> {
+        if auto_repeat.initial_delay_ms < 0 {
+            bail!("initial_delay_ms must be non-negative!");
+        }
+        if auto_repeat.interval_ms <= 0 {
+            bail!("interval_ms must be positive!");
+        }
+        Ok(Self {
+            initial_delay: Duration::from_millis(
+                auto_repeat.initial_delay_ms as u64,
+            ),
+            interval: Duration::from_millis(auto_repeat.interval_ms as u64),
+            repeating_keys: RefCell::new(None),
+        })
+    }
+}
+
+impl KeyRepeater {
+    /// Returns the key events to send right away.
+    pub fn start(&self, key_codes: &[c_char]) -> Vec<KeyEvent> {
+        self.repeating_keys.replace(Some(RepeatingKeys {
+            key_codes: key_codes.to_vec(),
+            next_repeat_time: Instant::now() + self.initial_delay,
+        }));
+        tap_keys(key_codes)
+    }
+
+    pub fn stop(&self) {
+        self.repeating_keys.replace(None);
+    }
+
+    pub fn tick(&self, now: Instant) -> Vec<KeyEvent> {
+        let mut repeating_keys = self.repeating_keys.borrow_mut();
+        let Some(repeating_keys) = repeating_keys.as_mut() else {
+            return vec![];
+        };
+        if now < repeating_keys.next_repeat_time {
+            return vec![];
+        }
+        // Don't try to catch up if the timer fired late, otherwise we would
+        // send a burst of key events.
+        repeating_keys.next_repeat_time += self.interval;
+        if repeating_keys.next_repeat_time < now {
+            repeating_keys.next_repeat_time = now + self.interval;
+        }
+        tap_keys(&repeating_keys.key_codes)
+    }
+}
+
+impl Display for KeyRepeater {
+    fn fmt(&self, formatter: &mut Formatter) -> FmtResult {
+        formatter.write_fmt(format_args!(
+            "{{initial delay: {:?}, interval: {:?}}}",
+            self.initial_delay, self.interval
+        ))
+    }
+}
+
+fn tap_keys(key_codes: &[c_char]) -> Vec<KeyEvent> {
+    key_codes
+        .iter()
+        .map(|&key_code| KeyEvent::PressAndRelease(key_code))
+        .collect()
+}
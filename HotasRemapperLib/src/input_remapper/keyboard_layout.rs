@@ -0,0 +1,149 @@
+use std::ffi::c_char;
+
+use crate::input_remapping::keyboard_macro::KeyboardLayout;
+
+const LEFT_SHIFT: u8 = 0xE1;
+// Also known as AltGr on European layouts.
+const RIGHT_ALT: u8 = 0xE6;
+
+enum Modifier {
+    None,
+    Shift,
+    AltGr,
+}
+
+/// Returns the keys to press together to type `character`, with the modifier
+/// key first, or None if the character can't be typed with the layout.
+pub(crate) fn get_key_codes(
+    layout: KeyboardLayout,
+    character: char,
+) -> Option<Vec<c_char>> {
+    let (modifier, key_code) = match layout {
+        KeyboardLayout::US => get_us_key(character),
+        KeyboardLayout::UK => get_uk_key(character),
+        KeyboardLayout::GERMAN => get_german_key(character),
+    }?;
+    let mut key_codes = match modifier {
+        Modifier::None => vec![],
+        Modifier::Shift => vec![LEFT_SHIFT as c_char],
+        Modifier::AltGr => vec![RIGHT_ALT as c_char],
+    };
+    key_codes.push(key_code as c_char);
+    Some(key_codes)
+}
+
+fn get_us_key(character: char) -> Option<(Modifier, u8)> {
+    Some(match character {
+        'a'..='z' => (Modifier::None, 0x04 + (character as u8 - b'a')),
+        'A'..='Z' => (Modifier::Shift, 0x04 + (character as u8 - b'A')),
+        '1'..='9' => (Modifier::None, 0x1E + (character as u8 - b'1')),
+        '0' => (Modifier::None, 0x27),
+        '\n' => (Modifier::None, 0x28),
+        '\t' => (Modifier::None, 0x2B),
+        ' ' => (Modifier::None, 0x2C),
+        '!' => (Modifier::Shift, 0x1E),
+        '@' => (Modifier::Shift, 0x1F),
+        '#' => (Modifier::Shift, 0x20),
+        '$' => (Modifier::Shift, 0x21),
+        '%' => (Modifier::Shift, 0x22),
+        '^' => (Modifier::Shift, 0x23),
+        '&' => (Modifier::Shift, 0x24),
+        '*' => (Modifier::Shift, 0x25),
+        '(' => (Modifier::Shift, 0x26),
+        ')' => (Modifier::Shift, 0x27),
+        '-' => (Modifier::None, 0x2D),
+        '_' => (Modifier::Shift, 0x2D),
+        '=' => (Modifier::None, 0x2E),
+        '+' => (Modifier::Shift, 0x2E),
+        '[' => (Modifier::None, 0x2F),
+        '{' => (Modifier::Shift, 0x2F),
+        ']' => (Modifier::None, 0x30),
+        '}' => (Modifier::Shift, 0x30),
+        '\\' => (Modifier::None, 0x31),
+        '|' => (Modifier::Shift, 0x31),
+        ';' => (Modifier::None, 0x33),
+        ':' => (Modifier::Shift, 0x33),
+        '\'' => (Modifier::None, 0x34),
+        '"' => (Modifier::Shift, 0x34),
+        '`' => (Modifier::None, 0x35),
+        '~' => (Modifier::Shift, 0x35),
+        ',' => (Modifier::None, 0x36),
+        '<' => (Modifier::Shift, 0x36),
+        '.' => (Modifier::None, 0x37),
+        '>' => (Modifier::Shift, 0x37),
+        '/' => (Modifier::None, 0x38),
+        '?' => (Modifier::Shift, 0x38),
+        _ => return None,
+    })
+}
+
+fn get_uk_key(character: char) -> Option<(Modifier, u8)> {
+    Some(match character {
+        '"' => (Modifier::Shift, 0x1F),
+        '£' => (Modifier::Shift, 0x20),
+        '@' => (Modifier::Shift, 0x34),
+        '¬' => (Modifier::Shift, 0x35),
+        '#' => (Modifier::None, 0x32),
+        '~' => (Modifier::Shift, 0x32),
+        '\\' => (Modifier::None, 0x64),
+        '|' => (Modifier::Shift, 0x64),
+        _ => return get_us_key(character),
+    })
+}
+
+fn get_german_key(character: char) -> Option<(Modifier, u8)> {
+    Some(match character {
+        // Y and Z are swapped on QWERTZ keyboards.
+        'y' => (Modifier::None, 0x1D),
+        'Y' => (Modifier::Shift, 0x1D),
+        'z' => (Modifier::None, 0x1C),
+        'Z' => (Modifier::Shift, 0x1C),
+        'a'..='z' | 'A'..='Z' | '0'..='9' | '\n' | '\t' | ' ' => {
+            return get_us_key(character)
+        }
+        '!' => (Modifier::Shift, 0x1E),
+        '"' => (Modifier::Shift, 0x1F),
+        '²' => (Modifier::AltGr, 0x1F),
+        '§' => (Modifier::Shift, 0x20),
+        '³' => (Modifier::AltGr, 0x20),
+        '$' => (Modifier::Shift, 0x21),
+        '%' => (Modifier::Shift, 0x22),
+        '&' => (Modifier::Shift, 0x23),
+        '/' => (Modifier::Shift, 0x24),
+        '{' => (Modifier::AltGr, 0x24),
+        '(' => (Modifier::Shift, 0x25),
+        '[' => (Modifier::AltGr, 0x25),
+        ')' => (Modifier::Shift, 0x26),
+        ']' => (Modifier::AltGr, 0x26),
+        '=' => (Modifier::Shift, 0x27),
+        '}' => (Modifier::AltGr, 0x27),
+        'ß' => (Modifier::None, 0x2D),
+        '?' => (Modifier::Shift, 0x2D),
+        '\\' => (Modifier::AltGr, 0x2D),
+        'ü' => (Modifier::None, 0x2F),
+        'Ü' => (Modifier::Shift, 0x2F),
+        '+' => (Modifier::None, 0x30),
+        '*' => (Modifier::Shift, 0x30),
+        '~' => (Modifier::AltGr, 0x30),
+        '#' => (Modifier::None, 0x32),
+        '\'' => (Modifier::Shift, 0x32),
+        'ö' => (Modifier::None, 0x33),
+        'Ö' => (Modifier::Shift, 0x33),
+        'ä' => (Modifier::None, 0x34),
+        'Ä' => (Modifier::Shift, 0x34),
+        '°' => (Modifier::Shift, 0x35),
+        ',' => (Modifier::None, 0x36),
+        ';' => (Modifier::Shift, 0x36),
+        '.' => (Modifier::None, 0x37),
+        ':' => (Modifier::Shift, 0x37),
+        '-' => (Modifier::None, 0x38),
+        '_' => (Modifier::Shift, 0x38),
+        '<' => (Modifier::None, 0x64),
+        '>' => (Modifier::Shift, 0x64),
+        '|' => (Modifier::AltGr, 0x64),
+        '@' => (Modifier::AltGr, 0x14),
+        '€' => (Modifier::AltGr, 0x08),
+        'µ' => (Modifier::AltGr, 0x10),
+        _ => return None,
+    })
+}
@@ -0,0 +1,51 @@
+use std::cell::Cell;
+use std::convert::TryFrom;
+use std::ffi::c_char;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+
+use super::convert_key_code;
+use super::RemapInputValue;
+use crate::input_remapping::LatchButtonInput;
+use crate::virtual_device::KeyEvent;
+
+pub(crate) struct LatchButtonRemapper {
+    key_code: c_char,
+    is_button_pressed: Cell<bool>,
+    is_key_latched: Cell<bool>,
+}
+
+impl TryFrom<&LatchButtonInput> for LatchButtonRemapper {
+    type Error = anyhow::Error;
+
+    fn try_from(input: &LatchButtonInput) -> Result<Self, Self::Error> {
+        Ok(Self {
+            key_code: convert_key_code(input.key_code)?,
+            is_button_pressed: Cell::new(false),
+            is_key_latched: Cell::new(false),
+        })
+    }
+}
+
+impl RemapInputValue for LatchButtonRemapper {
+    fn remap(&self, value: i32) -> Vec<KeyEvent> {
+        let is_pressed = value != 0;
+        if self.is_button_pressed.replace(is_pressed) || !is_pressed {
+            return vec![];
+        }
+        let was_latched = self.is_key_latched.get();
+        self.is_key_latched.set(!was_latched);
+        vec![if was_latched {
+            KeyEvent::Release(self.key_code)
+        } else {
+            KeyEvent::Press(self.key_code)
+        }]
+    }
+}
+
+impl Display for LatchButtonRemapper {
+    fn fmt(&self, formatter: &mut Formatter) -> FmtResult {
+        formatter.write_fmt(format_args!("latch {}", self.key_code))
+    }
+}
@@ -0,0 +1,64 @@
+use std::cell::Cell;
+use std::convert::TryFrom;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::time::Instant;
+
+use anyhow::anyhow;
+
+use super::macro_player::MacroPlayer;
+use super::RemapInputValue;
+use crate::input_remapping::MacroButtonInput;
+use crate::virtual_device::KeyEvent;
+
+pub(crate) struct MacroButtonRemapper {
+    macro_player: MacroPlayer,
+    stops_on_release: bool,
+    is_button_pressed: Cell<bool>,
+}
+
+impl TryFrom<&MacroButtonInput> for MacroButtonRemapper {
+    type Error = anyhow::Error;
+
+    fn try_from(input: &MacroButtonInput) -> Result<Self, Self::Error> {
+        let keyboard_macro = input
+            .keyboard_macro
+            .as_ref()
+            .ok_or_else(|| anyhow!("keyboard_macro is not provided!"))?;
+        Ok(Self {
+            macro_player: MacroPlayer::try_from(keyboard_macro)?,
+            stops_on_release: input.stops_on_release,
+            is_button_pressed: Cell::new(false),
+        })
+    }
+}
+
+impl RemapInputValue for MacroButtonRemapper {
+    fn remap(&self, value: i32) -> Vec<KeyEvent> {
+        let is_pressed = value != 0;
+        if self.is_button_pressed.replace(is_pressed) == is_pressed {
+            return vec![];
+        }
+        if is_pressed {
+            self.macro_player.start(Instant::now())
+        } else if self.stops_on_release {
+            self.macro_player.stop()
+        } else {
+            vec![]
+        }
+    }
+
+    fn tick(&self, now: Instant) -> Vec<KeyEvent> {
+        self.macro_player.tick(now)
+    }
+}
+
+impl Display for MacroButtonRemapper {
+    fn fmt(&self, formatter: &mut Formatter) -> FmtResult {
+        formatter.write_fmt(format_args!(
+            "macro {} (stops on release: {})",
+            self.macro_player, self.stops_on_release
+        ))
+    }
+}
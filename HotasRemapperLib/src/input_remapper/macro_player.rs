@@ -0,0 +1,210 @@
+use std::cell::RefCell;
+use std::convert::TryFrom;
+use std::ffi::c_char;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::time::Duration;
+use std::time::Instant;
+
+use anyhow::anyhow;
+use anyhow::bail;
+use anyhow::Result;
+
+use super::convert_key_codes;
+use super::keyboard_layout::get_key_codes;
+use crate::input_remapping::keyboard_macro::KeySequence;
+use crate::input_remapping::keyboard_macro::Text;
+use crate::input_remapping::KeyboardMacro;
+use crate::virtual_device::KeyEvent;
+
+const DEFAULT_TEXT_HOLD_MS: i32 = 20;
+const DEFAULT_TEXT_GAP_MS: i32 = 20;
+
+struct MacroStep {
+    key_codes: Vec<c_char>,
+    hold: Duration,
+    gap: Duration,
+}
+
+impl MacroStep {
+    fn new(key_codes: Vec<c_char>, hold_ms: i32, gap_ms: i32) -> Result<Self> {
+        if hold_ms < 0 || gap_ms < 0 {
+            bail!("hold_ms and gap_ms must be non-negative!");
+        }
+        Ok(Self {
+            key_codes,
+            hold: Duration::from_millis(hold_ms as u64),
+            gap: Duration::from_millis(gap_ms as u64),
+        })
+    }
+}
+
+#[derive(Clone, Copy)]
+enum MacroPhase {
+    Holding {
+        step_index: usize,
+        until: Instant,
+    },
+    Waiting {
+        next_step_index: usize,
+        until: Instant,
+    },
+}
+
+/// Plays a macro step by step, driven by `tick()`. The keys of the current
+/// step are released whenever the macro is stopped or restarted, so that
+/// nothing is left pressed.
+pub(crate) struct MacroPlayer {
+    steps: Vec<MacroStep>,
+    phase: RefCell<Option<MacroPhase>>,
+}
+
+impl TryFrom<&KeyboardMacro> for MacroPlayer {
+    type Error = anyhow::Error;
+
+    fn try_from(keyboard_macro: &KeyboardMacro) -> Result<Self, Self::Error> {
+        let steps = if keyboard_macro.has_key_sequence() {
+            convert_key_sequence(keyboard_macro.key_sequence())?
+        } else if keyboard_macro.has_text() {
+            convert_text(keyboard_macro.text())?
+        } else {
+            bail!("The macro is empty!");
+        };
+        if steps.is_empty() {
+            bail!("The macro is empty!");
+        }
+        Ok(Self {
+            steps,
+            phase: RefCell::new(None),
+        })
+    }
+}
+
+impl MacroPlayer {
+    /// Plays the macro from the start, even if it's already playing.
+    pub fn start(&self, now: Instant) -> Vec<KeyEvent> {
+        let mut key_events = self.stop();
+        self.phase.replace(Some(MacroPhase::Waiting {
+            next_step_index: 0,
+            until: now,
+        }));
+        key_events.append(&mut self.tick(now));
+        key_events
+    }
+
+    /// Returns the key events to release the keys held by the current step.
+    pub fn stop(&self) -> Vec<KeyEvent> {
+        match self.phase.replace(None) {
+            Some(MacroPhase::Holding { step_index, .. }) => {
+                release_keys(&self.steps[step_index].key_codes)
+            }
+            _ => vec![],
+        }
+    }
+
+    pub fn tick(&self, now: Instant) -> Vec<KeyEvent> {
+        let mut key_events = vec![];
+        let mut phase = self.phase.borrow_mut();
+        // Steps with zero timings are played within the same tick. Timings are
+        // counted from `now` rather than the deadline, so that a late timer
+        // doesn't shorten the next step.
+        while let Some(current_phase) = *phase {
+            match current_phase {
+                MacroPhase::Holding { step_index, until } if now >= until => {
+                    let step = &self.steps[step_index];
+                    key_events.append(&mut release_keys(&step.key_codes));
+                    *phase = Some(MacroPhase::Waiting {
+                        next_step_index: step_index + 1,
+                        until: now + step.gap,
+                    });
+                }
+                MacroPhase::Waiting {
+                    next_step_index,
+                    until,
+                } if now >= until => {
+                    let Some(step) = self.steps.get(next_step_index) else {
+                        *phase = None;
+                        break;
+                    };
+                    key_events.extend(
+                        step.key_codes.iter().cloned().map(KeyEvent::Press),
+                    );
+                    *phase = Some(MacroPhase::Holding {
+                        step_index: next_step_index,
+                        until: now + step.hold,
+                    });
+                }
+                _ => break,
+            }
+        }
+        key_events
+    }
+}
+
+impl Display for MacroPlayer {
+    fn fmt(&self, formatter: &mut Formatter) -> FmtResult {
+        formatter.write_str("[")?;
+        for (index, step) in self.steps.iter().enumerate() {
+            if index > 0 {
+                formatter.write_str(", ")?;
+            }
+            formatter.write_fmt(format_args!(
+                "{{{:?}, hold: {:?}, gap: {:?}}}",
+                step.key_codes, step.hold, step.gap
+            ))?;
+        }
+        formatter.write_str("]")
+    }
+}
+
+fn convert_key_sequence(key_sequence: &KeySequence) -> Result<Vec<MacroStep>> {
+    key_sequence
+        .steps
+        .iter()
+        .map(|step| {
+            MacroStep::new(
+                convert_key_codes(&step.key_codes)?,
+                step.hold_ms,
+                step.gap_ms,
+            )
+        })
+        .collect()
+}
+
+fn convert_text(text: &Text) -> Result<Vec<MacroStep>> {
+    let layout = text.layout.enum_value_or_default();
+    let hold_ms = match text.hold_ms {
+        0 => DEFAULT_TEXT_HOLD_MS,
+        hold_ms => hold_ms,
+    };
+    let gap_ms = match text.gap_ms {
+        0 => DEFAULT_TEXT_GAP_MS,
+        gap_ms => gap_ms,
+    };
+    text.text
+        .chars()
+        .map(|character| {
+            let key_codes =
+                get_key_codes(layout, character).ok_or_else(|| {
+                    anyhow!(
+                        "Cannot type {:?} with the {:?} layout",
+                        character,
+                        layout
+                    )
+                })?;
+            MacroStep::new(key_codes, hold_ms, gap_ms)
+        })
+        .collect()
+}
+
+/// Releases in the reverse order of pressing, so that modifier keys are
+/// released last.
+fn release_keys(key_codes: &[c_char]) -> Vec<KeyEvent> {
+    key_codes
+        .iter()
+        .rev()
+        .cloned()
+        .map(KeyEvent::Release)
+        .collect()
+}
@@ -1,34 +1,90 @@
+mod axis_deflection;
+mod axis_duty_cycle_remapper;
 mod axis_remapper;
+mod axis_repeat_rate_remapper;
+mod axis_throttle_follower_remapper;
+mod axis_zone_remapper;
 mod button_remapper;
+mod chord_layer;
+mod consumer_control_remapper;
+mod cycle_button_remapper;
+mod gamepad_axis_remapper;
+mod gamepad_button_remapper;
+mod gamepad_dpad_remapper;
 mod hat_switch_remapper;
+mod key_repeater;
+mod keyboard_layout;
+mod latch_button_remapper;
+mod macro_button_remapper;
+mod macro_player;
+mod mouse_axis_remapper;
+mod mouse_button_remapper;
+mod multi_position_switch_remapper;
+mod radio_group_remapper;
+mod rate_limited_remapper;
+mod rotary_encoder_remapper;
 mod toggle_switch_input;
+mod virtual_hat_switch_remapper;
 
 use std::collections::HashMap;
+use std::collections::HashSet;
 use std::convert::From;
 use std::convert::TryFrom;
 use std::ffi::c_char;
 use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
 use std::result::Result as StdResult;
+use std::time::Instant;
 
 use anyhow::anyhow;
 use anyhow::bail;
 use anyhow::Error;
 use anyhow::Result;
+use axis_duty_cycle_remapper::AxisDutyCycleRemapper;
 use axis_remapper::AxisRemapper;
+use axis_repeat_rate_remapper::AxisRepeatRateRemapper;
+use axis_throttle_follower_remapper::AxisThrottleFollowerRemapper;
+use axis_zone_remapper::AxisZoneRemapper;
 use button_remapper::ButtonRemapper;
+use chord_layer::ChordLayer;
+use consumer_control_remapper::ConsumerControlRemapper;
+use cycle_button_remapper::CycleButtonRemapper;
+use gamepad_axis_remapper::GamepadAxisRemapper;
+use gamepad_button_remapper::GamepadButtonRemapper;
+use gamepad_dpad_remapper::GamepadDpadRemapper;
 use hat_switch_remapper::HatSwitchRemapper;
+use latch_button_remapper::LatchButtonRemapper;
+use macro_button_remapper::MacroButtonRemapper;
+use mouse_axis_remapper::MouseAxisRemapper;
+use mouse_button_remapper::MouseButtonRemapper;
+use multi_position_switch_remapper::MultiPositionSwitchRemapper;
 use protobuf::text_format::parse_from_str as parse_proto_from_str;
+use radio_group_remapper::RadioGroupRemapper;
+use rate_limited_remapper::RateLimitedRemapper;
+use rotary_encoder_remapper::RotaryEncoderRemapper;
 use toggle_switch_input::ToggleSwitchRemapper;
+use virtual_hat_switch_remapper::VirtualHatSwitchRemapper;
 
 use crate::input_reader::hid_device::DeviceType;
 use crate::input_reader::hid_device::InputEvent;
 use crate::input_reader::hid_device_input::DeviceInput;
 use crate::input_reader::hid_device_input::InputType;
+use crate::input_remapping::input_reference::Device;
+use crate::input_remapping::AxisInput;
+use crate::input_remapping::InputReference;
 use crate::input_remapping::InputRemapping;
 use crate::input_remapping::RemappedInput;
+use crate::token_bucket::TokenBucket;
+use crate::virtual_device::ConsumerControlEvent;
+use crate::virtual_device::GamepadEvent;
 use crate::virtual_device::KeyEvent;
+use crate::virtual_device::KeySource;
+use crate::virtual_device::MouseEvent;
+use crate::virtual_device::OutputEvent;
+use crate::virtual_device::OutputKind;
 
-#[derive(Eq, Hash, PartialEq)]
+#[derive(Clone, Copy, Eq, Hash, PartialEq)]
 struct InputIdentifier {
     pub device_type: DeviceType,
     pub device_input: DeviceInput,
@@ -43,41 +99,254 @@ impl From<&InputEvent> for InputIdentifier {
     }
 }
 
-trait RemapInputValue: Display {
-    fn remap(&self, value: i32) -> Option<KeyEvent>;
+impl TryFrom<&InputReference> for InputIdentifier {
+    type Error = Error;
+
+    fn try_from(input: &InputReference) -> Result<Self, Self::Error> {
+        let device_type = match input.device.enum_value() {
+            Ok(Device::JOYSTICK) => DeviceType::Joystick,
+            Ok(Device::THROTTLE) => DeviceType::Throttle,
+            Err(value) => bail!("Unknown device: {}", value),
+        };
+        let input_type: InputType = match input.input_type.as_str().try_into() {
+            Ok(input_type) => input_type,
+            Err(_) => bail!("Unknown input type name: {}", input.input_type),
+        };
+        Ok(Self {
+            device_type,
+            device_input: DeviceInput {
+                input_type,
+                index: input.index,
+            },
+        })
+    }
+}
+
+impl Display for InputIdentifier {
+    fn fmt(&self, formatter: &mut Formatter) -> FmtResult {
+        formatter.write_fmt(format_args!(
+            "{:?} {}",
+            self.device_type, self.device_input
+        ))
+    }
+}
+
+/// `E` is the kind of events fired, which are key events for most remappers.
+trait RemapInputValue<E = KeyEvent>: Display {
+    fn remap(&self, value: i32) -> Vec<E>;
+
+    /// Called periodically, so that remappers can fire key events that are not
+    /// triggered by input events, e.g. to repeat a key.
+    fn tick(&self, _now: Instant) -> Vec<E> {
+        vec![]
+    }
+
+    /// Returns how many key presses were dropped by the rate limit of this
+    /// remapper.
+    fn num_suppressed(&self) -> u64 {
+        0
+    }
+}
+
+/// A remapper of a single input, which fires events of one output kind.
+enum OutputRemapper {
+    Keyboard(Box<dyn RemapInputValue>),
+    Mouse(Box<dyn RemapInputValue<MouseEvent>>),
+    Gamepad(Box<dyn RemapInputValue<GamepadEvent>>),
+    ConsumerControl(Box<dyn RemapInputValue<ConsumerControlEvent>>),
+}
+
+impl OutputRemapper {
+    fn output_kind(&self) -> OutputKind {
+        match self {
+            OutputRemapper::Keyboard(_) => OutputKind::Keyboard,
+            OutputRemapper::Mouse(_) => OutputKind::Mouse,
+            OutputRemapper::Gamepad(_) => OutputKind::Gamepad,
+            OutputRemapper::ConsumerControl(_) => OutputKind::ConsumerControl,
+        }
+    }
+
+    fn num_suppressed(&self) -> u64 {
+        match self {
+            OutputRemapper::Keyboard(remapper) => remapper.num_suppressed(),
+            OutputRemapper::Mouse(_)
+            | OutputRemapper::Gamepad(_)
+            | OutputRemapper::ConsumerControl(_) => 0,
+        }
+    }
+
+    fn remap(
+        &self,
+        source: &KeySource,
+        value: i32,
+    ) -> Vec<(KeySource, OutputEvent)> {
+        match self {
+            OutputRemapper::Keyboard(remapper) => {
+                with_source(source, remapper.remap(value))
+            }
+            OutputRemapper::Mouse(remapper) => {
+                with_source(source, remapper.remap(value))
+            }
+            OutputRemapper::Gamepad(remapper) => {
+                with_source(source, remapper.remap(value))
+            }
+            OutputRemapper::ConsumerControl(remapper) => {
+                with_source(source, remapper.remap(value))
+            }
+        }
+    }
+
+    fn tick(
+        &self,
+        source: &KeySource,
+        now: Instant,
+    ) -> Vec<(KeySource, OutputEvent)> {
+        match self {
+            OutputRemapper::Keyboard(remapper) => {
+                with_source(source, remapper.tick(now))
+            }
+            OutputRemapper::Mouse(remapper) => {
+                with_source(source, remapper.tick(now))
+            }
+            OutputRemapper::Gamepad(remapper) => {
+                with_source(source, remapper.tick(now))
+            }
+            OutputRemapper::ConsumerControl(remapper) => {
+                with_source(source, remapper.tick(now))
+            }
+        }
+    }
+}
+
+impl Display for OutputRemapper {
+    fn fmt(&self, formatter: &mut Formatter) -> FmtResult {
+        match self {
+            OutputRemapper::Keyboard(remapper) => remapper.fmt(formatter),
+            OutputRemapper::Mouse(remapper) => remapper.fmt(formatter),
+            OutputRemapper::Gamepad(remapper) => remapper.fmt(formatter),
+            OutputRemapper::ConsumerControl(remapper) => {
+                remapper.fmt(formatter)
+            }
+        }
+    }
+}
+
+/// Same as `RemapInputValue`, but for remappers that combine several inputs.
+/// `input_index` is the position of the input in the remapping.
+trait RemapMultiInputValue: Display {
+    fn remap(&self, input_index: usize, value: i32) -> Vec<KeyEvent>;
+
+    fn tick(&self, _now: Instant) -> Vec<KeyEvent> {
+        vec![]
+    }
 }
 
 pub(crate) struct InputRemapper {
-    input_remappers: HashMap<InputIdentifier, Box<dyn RemapInputValue>>,
+    input_remappers: HashMap<InputIdentifier, (KeySource, OutputRemapper)>,
+    multi_input_remappers: Vec<(KeySource, Box<dyn RemapMultiInputValue>)>,
+    // Maps each input to the index of its multi-input remapper and its index
+    // within that remapper.
+    multi_input_indices: HashMap<InputIdentifier, (usize, usize)>,
+    chord_layer: ChordLayer,
+    // Inputs can only be remapped to these outputs.
+    output_kinds: Vec<OutputKind>,
 }
 
 impl InputRemapper {
-    pub fn new() -> Self {
+    pub fn new(output_kinds: &[OutputKind]) -> Self {
         Self {
             input_remappers: Default::default(),
+            multi_input_remappers: Default::default(),
+            multi_input_indices: Default::default(),
+            chord_layer: ChordLayer::new(),
+            output_kinds: output_kinds.to_vec(),
         }
     }
 
+    /// Replaces the current remapping. If it fails, the current remapping is
+    /// kept as is.
     pub fn load_input_remapping(
         &mut self,
         encoded_input_remapping: &str,
     ) -> Result<()> {
-        self.input_remappers.clear();
         let input_remapping =
             parse_proto_from_str::<InputRemapping>(&encoded_input_remapping)
                 .map_err(|e| anyhow!("Failed to parse as text proto: {}", e))?;
-        self.load_remapping_for_device(&input_remapping, DeviceType::Joystick)?;
-        self.load_remapping_for_device(&input_remapping, DeviceType::Throttle)?;
+        let mut input_remapper = Self::new(&self.output_kinds);
+        input_remapper.load_remapping_for_device(
+            &input_remapping,
+            DeviceType::Joystick,
+        )?;
+        input_remapper.load_remapping_for_device(
+            &input_remapping,
+            DeviceType::Throttle,
+        )?;
+        input_remapper.load_multi_input_remapping(&input_remapping)?;
+        println!("Chords:");
+        for chord in input_remapping.button_chords.iter() {
+            input_remapper.chord_layer.add_chord(chord)?;
+        }
+        *self = input_remapper;
         Ok(())
     }
 
+    /// Returns the output events along with the sources that fire them.
     pub fn remap_input_event(
         &self,
         input_event: &InputEvent,
-    ) -> Option<KeyEvent> {
+    ) -> Vec<(KeySource, OutputEvent)> {
+        let input_identifier = input_event.into();
+        let chord_layer_output =
+            self.chord_layer.remap(input_identifier, input_event.value);
+        let mut output_events: Vec<(KeySource, OutputEvent)> =
+            chord_layer_output
+                .inputs_to_release
+                .iter()
+                .flat_map(|input| self.remap_input_value(input, 0))
+                .collect();
+        output_events.extend(
+            chord_layer_output
+                .key_events
+                .into_iter()
+                .map(|(source, key_event)| (source, key_event.into())),
+        );
+        if chord_layer_output.should_forward {
+            output_events.append(
+                &mut self
+                    .remap_input_value(&input_identifier, input_event.value),
+            );
+        }
+        output_events
+    }
+
+    pub fn tick(&self, now: Instant) -> Vec<(KeySource, OutputEvent)> {
         self.input_remappers
-            .get(&input_event.into())
-            .and_then(|remapper| remapper.remap(input_event.value))
+            .values()
+            .flat_map(|(source, remapper)| remapper.tick(source, now))
+            .chain(self.multi_input_remappers.iter().flat_map(
+                |(source, remapper)| with_source(source, remapper.tick(now)),
+            ))
+            .collect()
+    }
+
+    fn remap_input_value(
+        &self,
+        input_identifier: &InputIdentifier,
+        value: i32,
+    ) -> Vec<(KeySource, OutputEvent)> {
+        if let Some((source, remapper)) =
+            self.input_remappers.get(input_identifier)
+        {
+            return remapper.remap(source, value);
+        }
+        self.multi_input_indices
+            .get(input_identifier)
+            .map(|&(remapper_index, input_index)| {
+                let (source, remapper) =
+                    &self.multi_input_remappers[remapper_index];
+                with_source(source, remapper.remap(input_index, value))
+            })
+            .unwrap_or_default()
     }
 
     fn load_remapping_for_device(
@@ -103,21 +372,163 @@ impl InputRemapper {
                     input_type,
                     index: *index,
                 };
+                let input_identifier = InputIdentifier {
+                    device_type,
+                    device_input,
+                };
                 let input_remapper = Self::create_input_remapper(input)?;
+                let output_kind = input_remapper.output_kind();
+                if !self.output_kinds.contains(&output_kind) {
+                    bail!(
+                        "{} is remapped to {:?}, which is not enabled in \
+                        additional_outputs",
+                        input_identifier,
+                        output_kind
+                    );
+                }
                 println!("\t{} -> {}", device_input, input_remapper);
                 self.input_remappers.insert(
-                    InputIdentifier {
-                        device_type,
-                        device_input,
-                    },
-                    input_remapper,
+                    input_identifier,
+                    (
+                        KeySource::new(input_identifier.to_string()),
+                        input_remapper,
+                    ),
                 );
             }
         }
         Ok(())
     }
 
-    fn create_input_remapper(
+    fn load_multi_input_remapping(
+        &mut self,
+        input_remapping: &InputRemapping,
+    ) -> Result<()> {
+        println!("Remapping for combined inputs:");
+        for switch in input_remapping.multi_position_switches.iter() {
+            self.add_multi_input_remapper(
+                &switch.inputs,
+                Box::new(MultiPositionSwitchRemapper::try_from(switch)?),
+            )?;
+        }
+        for encoder in input_remapping.rotary_encoders.iter() {
+            self.add_multi_input_remapper(
+                &encoder.inputs,
+                Box::new(RotaryEncoderRemapper::try_from(encoder)?),
+            )?;
+        }
+        for hat_switch in input_remapping.virtual_hat_switches.iter() {
+            self.add_multi_input_remapper(
+                &hat_switch.inputs,
+                Box::new(VirtualHatSwitchRemapper::try_from(hat_switch)?),
+            )?;
+        }
+        for radio_group in input_remapping.radio_groups.iter() {
+            self.add_multi_input_remapper(
+                &radio_group.inputs,
+                Box::new(RadioGroupRemapper::try_from(radio_group)?),
+            )?;
+        }
+        Ok(())
+    }
+
+    fn add_multi_input_remapper(
+        &mut self,
+        inputs: &[InputReference],
+        input_remapper: Box<dyn RemapMultiInputValue>,
+    ) -> Result<()> {
+        let input_identifiers = inputs
+            .iter()
+            .map(InputIdentifier::try_from)
+            .collect::<Result<Vec<_>>>()?;
+        // Checked before inserting anything, so that the indices never refer
+        // to a remapper that isn't added.
+        let mut seen_identifiers = HashSet::new();
+        for input_identifier in input_identifiers.iter() {
+            if self.input_remappers.contains_key(input_identifier)
+                || self.multi_input_indices.contains_key(input_identifier)
+                || !seen_identifiers.insert(input_identifier)
+            {
+                bail!("{} is remapped more than once", input_identifier);
+            }
+        }
+        let remapper_index = self.multi_input_remappers.len();
+        for (input_index, input_identifier) in
+            input_identifiers.iter().enumerate()
+        {
+            self.multi_input_indices
+                .insert(*input_identifier, (remapper_index, input_index));
+        }
+        let source_name = format_input_identifiers(&input_identifiers);
+        println!("\t{} -> {}", source_name, input_remapper);
+        self.multi_input_remappers
+            .push((KeySource::new(source_name), input_remapper));
+        Ok(())
+    }
+
+    /// Returns how many key presses were dropped by the rate limits of the
+    /// inputs since the input remapping was loaded.
+    pub fn num_suppressed_key_presses(&self) -> u64 {
+        self.input_remappers
+            .values()
+            .map(|(_, remapper)| remapper.num_suppressed())
+            .sum()
+    }
+
+    // Multi-input remappers and chords can't have a rate limit of their own.
+    // Their key presses are only limited by `key_rate_limit` of the virtual
+    // device.
+    fn create_input_remapper(input: &RemappedInput) -> Result<OutputRemapper> {
+        if let Some(output_remapper) = Self::create_non_key_remapper(input)? {
+            if input.rate_limit.is_some() {
+                bail!(
+                    "rate_limit is only supported for inputs remapped to keys!"
+                );
+            }
+            return Ok(output_remapper);
+        }
+        let remapper = Self::create_key_remapper(input)?;
+        Ok(OutputRemapper::Keyboard(match input.rate_limit.as_ref() {
+            Some(rate_limit) => Box::new(RateLimitedRemapper::new(
+                remapper,
+                TokenBucket::try_from(rate_limit)?,
+            )),
+            None => remapper,
+        }))
+    }
+
+    fn create_non_key_remapper(
+        input: &RemappedInput,
+    ) -> Result<Option<OutputRemapper>> {
+        Ok(Some(if input.has_mouse_axis_input() {
+            OutputRemapper::Mouse(Box::new(MouseAxisRemapper::try_from(
+                input.mouse_axis_input(),
+            )?))
+        } else if input.has_mouse_button_input() {
+            OutputRemapper::Mouse(Box::new(MouseButtonRemapper::try_from(
+                input.mouse_button_input(),
+            )?))
+        } else if input.has_gamepad_axis_input() {
+            OutputRemapper::Gamepad(Box::new(GamepadAxisRemapper::try_from(
+                input.gamepad_axis_input(),
+            )?))
+        } else if input.has_gamepad_button_input() {
+            OutputRemapper::Gamepad(Box::new(GamepadButtonRemapper::try_from(
+                input.gamepad_button_input(),
+            )?))
+        } else if input.has_gamepad_dpad_input() {
+            OutputRemapper::Gamepad(Box::new(GamepadDpadRemapper::new()))
+        } else if input.has_consumer_control_input() {
+            OutputRemapper::ConsumerControl(Box::new(
+                ConsumerControlRemapper::try_from(
+                    input.consumer_control_input(),
+                )?,
+            ))
+        } else {
+            return Ok(None);
+        }))
+    }
+
+    fn create_key_remapper(
         input: &RemappedInput,
     ) -> Result<Box<dyn RemapInputValue>> {
         Ok(if input.has_button_input() {
@@ -129,11 +540,33 @@ impl InputRemapper {
         } else if input.has_hat_switch_input() {
             Box::new(HatSwitchRemapper::try_from(input.hat_switch_input())?)
         } else if input.has_axis_input() {
-            Box::new(AxisRemapper::try_from(input.axis_input())?)
+            Self::create_axis_remapper(input.axis_input())?
+        } else if input.has_axis_zone_input() {
+            Box::new(AxisZoneRemapper::try_from(input.axis_zone_input())?)
+        } else if input.has_latch_button_input() {
+            Box::new(LatchButtonRemapper::try_from(input.latch_button_input())?)
+        } else if input.has_cycle_button_input() {
+            Box::new(CycleButtonRemapper::try_from(input.cycle_button_input())?)
+        } else if input.has_macro_button_input() {
+            Box::new(MacroButtonRemapper::try_from(input.macro_button_input())?)
         } else {
             unreachable!()
         })
     }
+
+    fn create_axis_remapper(
+        input: &AxisInput,
+    ) -> Result<Box<dyn RemapInputValue>> {
+        Ok(if input.has_repeat_rate_mode() {
+            Box::new(AxisRepeatRateRemapper::try_from(input)?)
+        } else if input.has_duty_cycle_mode() {
+            Box::new(AxisDutyCycleRemapper::try_from(input)?)
+        } else if input.has_throttle_follower_mode() {
+            Box::new(AxisThrottleFollowerRemapper::try_from(input)?)
+        } else {
+            Box::new(AxisRemapper::try_from(input)?)
+        })
+    }
 }
 
 impl TryFrom<&str> for InputType {
@@ -155,11 +588,183 @@ impl TryFrom<&str> for InputType {
     }
 }
 
+fn with_source<E: Into<OutputEvent>>(
+    source: &KeySource,
+    events: Vec<E>,
+) -> Vec<(KeySource, OutputEvent)> {
+    events
+        .into_iter()
+        .map(|event| (source.clone(), event.into()))
+        .collect()
+}
+
+fn format_input_identifiers(input_identifiers: &[InputIdentifier]) -> String {
+    let names: Vec<String> = input_identifiers
+        .iter()
+        .map(InputIdentifier::to_string)
+        .collect();
+    format!("[{}]", names.join(", "))
+}
+
+/// Key codes are usages on the HID keyboard page, which fit in one byte.
+/// Modifier keys (0xE0 to 0xE7) don't fit in a signed char, so the byte is
+/// reinterpreted rather than range-checked as a char.
 fn convert_key_code(key_code: i32) -> StdResult<c_char, Error> {
-    c_char::try_from(key_code)
+    u8::try_from(key_code)
+        .map(|key_code| key_code as c_char)
         .map_err(|e| anyhow!("Cannot convert {} to char: {}", key_code, e))
 }
 
 fn convert_key_codes(key_codes: &[i32]) -> StdResult<Vec<c_char>, Error> {
     key_codes.iter().cloned().map(convert_key_code).collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_remapper() -> InputRemapper {
+        InputRemapper::new(&[OutputKind::Keyboard])
+    }
+
+    fn press_button(
+        remapper: &InputRemapper,
+        index: i32,
+    ) -> Vec<(KeySource, OutputEvent)> {
+        remapper.remap_input_event(&InputEvent {
+            device_type: DeviceType::Joystick,
+            device_input: DeviceInput {
+                input_type: InputType::Button,
+                index,
+            },
+            value: 1,
+        })
+    }
+
+    #[test]
+    fn rejects_input_claimed_by_another_switch() {
+        let mut remapper = create_remapper();
+
+        let error = remapper
+            .load_input_remapping(
+                r#"
+                multi_position_switches {
+                  inputs { device: JOYSTICK input_type: "button" index: 2 }
+                  key_codes: 4 key_codes: 0
+                }
+                multi_position_switches {
+                  inputs { device: JOYSTICK input_type: "button" index: 3 }
+                  inputs { device: JOYSTICK input_type: "button" index: 2 }
+                  key_codes: 5 key_codes: 6 key_codes: 0
+                }
+                "#,
+            )
+            .unwrap_err();
+
+        assert!(
+            error.to_string().contains("remapped more than once"),
+            "{:?}",
+            error
+        );
+        assert!(press_button(&remapper, 3).is_empty());
+    }
+
+    #[test]
+    fn rejects_input_listed_twice() {
+        let mut remapper = create_remapper();
+
+        let error = remapper
+            .load_input_remapping(
+                r#"
+                radio_groups {
+                  inputs { device: JOYSTICK input_type: "button" index: 1 }
+                  inputs { device: JOYSTICK input_type: "button" index: 1 }
+                  key_codes: 4 key_codes: 5
+                }
+                "#,
+            )
+            .unwrap_err();
+
+        assert!(
+            error.to_string().contains("remapped more than once"),
+            "{:?}",
+            error
+        );
+        assert!(press_button(&remapper, 1).is_empty());
+    }
+
+    #[test]
+    fn rejects_chord_with_input_listed_twice() {
+        let mut remapper = create_remapper();
+
+        let error = remapper
+            .load_input_remapping(
+                r#"
+                button_chords {
+                  inputs { device: JOYSTICK input_type: "button" index: 1 }
+                  inputs { device: THROTTLE input_type: "button" index: 2 }
+                  inputs { device: JOYSTICK input_type: "button" index: 1 }
+                  key_code: 4
+                }
+                "#,
+            )
+            .unwrap_err();
+
+        assert!(
+            error
+                .to_string()
+                .contains("appears more than once in a chord"),
+            "{:?}",
+            error
+        );
+        assert!(press_button(&remapper, 1).is_empty());
+    }
+
+    #[test]
+    fn keeps_current_remapping_when_loading_fails() {
+        let mut remapper = create_remapper();
+        remapper
+            .load_input_remapping(
+                r#"
+                joystick_inputs {
+                  key: "button"
+                  value {
+                    inputs {
+                      key: 1
+                      value { button_input { key_code: 4 } }
+                    }
+                  }
+                }
+                "#,
+            )
+            .unwrap();
+
+        let error = remapper
+            .load_input_remapping(
+                r#"
+                joystick_inputs {
+                  key: "button"
+                  value {
+                    inputs {
+                      key: 2
+                      value { button_input { key_code: 5 } }
+                    }
+                  }
+                }
+                radio_groups {
+                  inputs { device: JOYSTICK input_type: "button" index: 2 }
+                  key_codes: 6
+                }
+                "#,
+            )
+            .unwrap_err();
+
+        assert!(
+            error.to_string().contains("remapped more than once"),
+            "{:?}",
+            error
+        );
+        assert_eq!(press_button(&remapper, 1).len(), 1);
+        assert!(press_button(&remapper, 2).is_empty());
+    }
+}
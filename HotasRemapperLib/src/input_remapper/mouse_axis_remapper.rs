@@ -0,0 +1,112 @@
+use std::cell::Cell;
+use std::convert::TryFrom;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::time::Instant;
+
+use anyhow::bail;
+
+use super::axis_deflection::AxisDeflection;
+use super::RemapInputValue;
+use crate::input_remapping::mouse_axis_input::MouseAxis as MouseAxisProto;
+use crate::input_remapping::MouseAxisInput;
+use crate::virtual_device::MouseAxis;
+use crate::virtual_device::MouseEvent;
+
+pub(crate) struct MouseAxisRemapper {
+    mouse_axis: MouseAxis,
+    axis_deflection: AxisDeflection,
+    max_speed: f64,
+    curve_exponent: f64,
+    // Counts per second, negative for moving backwards.
+    speed: Cell<f64>,
+    last_tick_time: Cell<Option<Instant>>,
+    // The fraction of a count that is not sent yet.
+    remainder: Cell<f64>,
+}
+
+impl TryFrom<&MouseAxisInput> for MouseAxisRemapper {
+    type Error = anyhow::Error;
+
+    fn try_from(input: &MouseAxisInput) -> Result<Self, Self::Error> {
+        let mouse_axis = match input.mouse_axis.enum_value() {
+            Ok(MouseAxisProto::X) => MouseAxis::X,
+            Ok(MouseAxisProto::Y) => MouseAxis::Y,
+            Ok(MouseAxisProto::WHEEL) => MouseAxis::Wheel,
+            Err(value) => bail!("Unknown mouse axis: {}", value),
+        };
+        if input.max_speed <= 0.0 {
+            bail!("max_speed must be positive!");
+        }
+        if input.curve_exponent < 0.0 {
+            bail!("curve_exponent must be non-negative!");
+        }
+        Ok(Self {
+            mouse_axis,
+            axis_deflection: AxisDeflection::new(
+                input.min_value,
+                input.max_value,
+                input.reverse_axis,
+                input.deadzone,
+            )?,
+            max_speed: input.max_speed as f64,
+            curve_exponent: if input.curve_exponent == 0.0 {
+                1.0
+            } else {
+                input.curve_exponent as f64
+            },
+            speed: Cell::new(0.0),
+            last_tick_time: Cell::new(None),
+            remainder: Cell::new(0.0),
+        })
+    }
+}
+
+impl RemapInputValue<MouseEvent> for MouseAxisRemapper {
+    fn remap(&self, value: i32) -> Vec<MouseEvent> {
+        let deflection = self.axis_deflection.deflection(value);
+        self.speed.set(
+            deflection.signum()
+                * self.max_speed
+                * deflection.abs().powf(self.curve_exponent),
+        );
+        vec![]
+    }
+
+    fn tick(&self, now: Instant) -> Vec<MouseEvent> {
+        let last_tick_time = self.last_tick_time.replace(Some(now));
+        let speed = self.speed.get();
+        if speed == 0.0 {
+            self.remainder.set(0.0);
+            return vec![];
+        }
+        let Some(last_tick_time) = last_tick_time else {
+            return vec![];
+        };
+        let distance =
+            self.remainder.get() + speed * (now - last_tick_time).as_secs_f64();
+        let whole_distance = distance.trunc();
+        self.remainder.set(distance - whole_distance);
+        if whole_distance == 0.0 {
+            return vec![];
+        }
+        vec![MouseEvent::Move {
+            axis: self.mouse_axis,
+            distance: whole_distance as i32,
+        }]
+    }
+}
+
+impl Display for MouseAxisRemapper {
+    fn fmt(&self, formatter: &mut Formatter) -> FmtResult {
+        formatter.write_fmt(format_args!(
+            "{{mouse axis: {:?}, axis: {}, max speed: {}/s, curve exponent: \
+            {}}}",
+            self.mouse_axis,
+            self.axis_deflection,
+            self.max_speed,
+            self.curve_exponent,
+        ))
+    }
+}
@@ -0,0 +1,43 @@
+use std::convert::TryFrom;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+
+use anyhow::bail;
+
+use super::RemapInputValue;
+use crate::input_remapping::MouseButtonInput;
+use crate::virtual_device::MouseEvent;
+
+pub(crate) struct MouseButtonRemapper {
+    mouse_button: u8,
+}
+
+impl TryFrom<&MouseButtonInput> for MouseButtonRemapper {
+    type Error = anyhow::Error;
+
+    fn try_from(input: &MouseButtonInput) -> Result<Self, Self::Error> {
+        let mouse_button = match input.mouse_button.enum_value() {
+            Ok(mouse_button) => mouse_button as u8,
+            Err(value) => bail!("Unknown mouse button: {}", value),
+        };
+        Ok(Self { mouse_button })
+    }
+}
+
+impl RemapInputValue<MouseEvent> for MouseButtonRemapper {
+    fn remap(&self, value: i32) -> Vec<MouseEvent> {
+        vec![if value != 0 {
+            MouseEvent::Press(self.mouse_button)
+        } else {
+            MouseEvent::Release(self.mouse_button)
+        }]
+    }
+}
+
+impl Display for MouseButtonRemapper {
+    fn fmt(&self, formatter: &mut Formatter) -> FmtResult {
+        formatter
+            .write_fmt(format_args!("{{mouse button: {}}}", self.mouse_button))
+    }
+}
@@ -0,0 +1,84 @@
+use std::cell::RefCell;
+use std::convert::TryFrom;
+use std::ffi::c_char;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+
+use anyhow::bail;
+
+use super::convert_key_codes;
+use super::RemapMultiInputValue;
+use crate::input_remapping::MultiPositionSwitch;
+use crate::virtual_device::KeyEvent;
+
+pub(crate) struct MultiPositionSwitchRemapper {
+    // The last one is for the position where no input is pressed.
+    key_codes: Vec<c_char>,
+    input_states: RefCell<Vec<bool>>,
+    last_position: RefCell<Option<usize>>,
+}
+
+impl TryFrom<&MultiPositionSwitch> for MultiPositionSwitchRemapper {
+    type Error = anyhow::Error;
+
+    fn try_from(input: &MultiPositionSwitch) -> Result<Self, Self::Error> {
+        let num_inputs = input.inputs.len();
+        if num_inputs == 0 {
+            bail!("No inputs provided!");
+        }
+        if input.key_codes.len() != num_inputs + 1 {
+            bail!(
+                "Number of key codes ({}) must be one more than the number of \
+                inputs ({})",
+                input.key_codes.len(),
+                num_inputs
+            );
+        }
+        Ok(Self {
+            key_codes: convert_key_codes(&input.key_codes)?,
+            input_states: RefCell::new(vec![false; num_inputs]),
+            last_position: RefCell::new(None),
+        })
+    }
+}
+
+impl MultiPositionSwitchRemapper {
+    /// Returns None if more than one input is pressed, which only happens
+    /// transiently while the switch is being moved.
+    fn get_position(&self) -> Option<usize> {
+        let input_states = self.input_states.borrow();
+        let mut pressed_inputs = input_states
+            .iter()
+            .enumerate()
+            .filter(|(_, &is_pressed)| is_pressed)
+            .map(|(index, _)| index);
+        match (pressed_inputs.next(), pressed_inputs.next()) {
+            (None, _) => Some(input_states.len()),
+            (Some(index), None) => Some(index),
+            (Some(_), Some(_)) => None,
+        }
+    }
+}
+
+impl RemapMultiInputValue for MultiPositionSwitchRemapper {
+    fn remap(&self, input_index: usize, value: i32) -> Vec<KeyEvent> {
+        self.input_states.borrow_mut()[input_index] = value != 0;
+        let Some(position) = self.get_position() else {
+            return vec![];
+        };
+        if self.last_position.replace(Some(position)) == Some(position) {
+            return vec![];
+        }
+        match self.key_codes[position] {
+            0 => vec![],
+            key_code => vec![KeyEvent::PressAndRelease(key_code)],
+        }
+    }
+}
+
+impl Display for MultiPositionSwitchRemapper {
+    fn fmt(&self, formatter: &mut Formatter) -> FmtResult {
+        formatter.write_fmt(format_args!("{:?}", self.key_codes))
+    }
+}
@@ -0,0 +1,71 @@
+use std::cell::Cell;
+use std::convert::TryFrom;
+use std::ffi::c_char;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+
+use anyhow::bail;
+
+use super::convert_key_codes;
+use super::RemapMultiInputValue;
+use crate::input_remapping::RadioGroup;
+use crate::virtual_device::KeyEvent;
+
+pub(crate) struct RadioGroupRemapper {
+    key_codes: Vec<c_char>,
+    selected_index: Cell<Option<usize>>,
+}
+
+impl TryFrom<&RadioGroup> for RadioGroupRemapper {
+    type Error = anyhow::Error;
+
+    fn try_from(input: &RadioGroup) -> Result<Self, Self::Error> {
+        if input.inputs.is_empty() {
+            bail!("No inputs provided!");
+        }
+        if input.key_codes.len() != input.inputs.len() {
+            bail!(
+                "Number of key codes ({}) doesn't match the number of inputs \
+                ({})",
+                input.key_codes.len(),
+                input.inputs.len()
+            );
+        }
+        Ok(Self {
+            key_codes: convert_key_codes(&input.key_codes)?,
+            selected_index: Cell::new(None),
+        })
+    }
+}
+
+impl RemapMultiInputValue for RadioGroupRemapper {
+    fn remap(&self, input_index: usize, value: i32) -> Vec<KeyEvent> {
+        if value == 0 {
+            return vec![];
+        }
+        let last_selected_index =
+            self.selected_index.replace(Some(input_index));
+        let to_release = match last_selected_index {
+            Some(index) if index == input_index => return vec![],
+            Some(index) => self.key_codes[index],
+            None => 0,
+        };
+        let to_press = self.key_codes[input_index];
+        match (to_release, to_press) {
+            (0, 0) => vec![],
+            (0, to_press) => vec![KeyEvent::Press(to_press)],
+            (to_release, 0) => vec![KeyEvent::Release(to_release)],
+            (to_release, to_press) => vec![KeyEvent::ReleaseAndPress {
+                to_release,
+                to_press,
+            }],
+        }
+    }
+}
+
+impl Display for RadioGroupRemapper {
+    fn fmt(&self, formatter: &mut Formatter) -> FmtResult {
+        formatter.write_fmt(format_args!("radio group {:?}", self.key_codes))
+    }
+}
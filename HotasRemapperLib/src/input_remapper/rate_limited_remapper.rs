@@ -0,0 +1,67 @@
+use std::cell::RefCell;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::time::Instant;
+
+use super::RemapInputValue;
+use crate::token_bucket::TokenBucket;
+use crate::virtual_device::KeyEvent;
+
+/// Drops the key presses fired by another remapper beyond its rate limit.
+pub(crate) struct RateLimitedRemapper {
+    remapper: Box<dyn RemapInputValue>,
+    token_bucket: RefCell<TokenBucket>,
+}
+
+impl RateLimitedRemapper {
+    pub fn new(
+        remapper: Box<dyn RemapInputValue>,
+        token_bucket: TokenBucket,
+    ) -> Self {
+        Self {
+            remapper,
+            token_bucket: RefCell::new(token_bucket),
+        }
+    }
+
+    fn limit(&self, key_events: Vec<KeyEvent>, now: Instant) -> Vec<KeyEvent> {
+        let mut token_bucket = self.token_bucket.borrow_mut();
+        key_events
+            .into_iter()
+            .filter_map(|key_event| {
+                if key_event.pressed_key_code().is_none()
+                    || token_bucket.try_take(now)
+                {
+                    Some(key_event)
+                } else {
+                    key_event.without_press()
+                }
+            })
+            .collect()
+    }
+}
+
+impl RemapInputValue for RateLimitedRemapper {
+    fn remap(&self, value: i32) -> Vec<KeyEvent> {
+        self.limit(self.remapper.remap(value), Instant::now())
+    }
+
+    fn tick(&self, now: Instant) -> Vec<KeyEvent> {
+        self.limit(self.remapper.tick(now), now)
+    }
+
+    fn num_suppressed(&self) -> u64 {
+        self.token_bucket.borrow().num_suppressed()
+    }
+}
+
+impl Display for RateLimitedRemapper {
+    fn fmt(&self, formatter: &mut Formatter) -> FmtResult {
+        formatter.write_fmt(format_args!(
+            "{} (rate limit: {})",
+            self.remapper,
+            self.token_bucket.borrow()
+        ))
+    }
+}
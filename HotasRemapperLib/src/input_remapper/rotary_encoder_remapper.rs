@@ -0,0 +1,272 @@
+use std::cell::RefCell;
+use std::convert::TryFrom;
+use std::ffi::c_char;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::time::Duration;
+use std::time::Instant;
+
+use anyhow::bail;
+
+use super::convert_key_codes;
+use super::RemapMultiInputValue;
+use crate::input_remapping::rotary_encoder::Encoding;
+use crate::input_remapping::EncoderAcceleration;
+use crate::input_remapping::RotaryEncoder;
+use crate::virtual_device::KeyEvent;
+
+const DEFAULT_TRANSITIONS_PER_DETENT: i32 = 4;
+
+/// The states of the two quadrature channels (the first input as the high bit)
+/// in the order of incrementing.
+const QUADRATURE_SEQUENCE: [usize; 4] = [0b00, 0b10, 0b11, 0b01];
+
+struct Acceleration {
+    fast_interval: Duration,
+    max_multiplier: i32,
+}
+
+impl TryFrom<&EncoderAcceleration> for Acceleration {
+    type Error = anyhow::Error;
+
+    fn try_from(
+        acceleration: &EncoderAcceleration,
+    ) -> Result<Self, Self::Error> {
+        if acceleration.fast_interval_ms <= 0 {
+            bail!("fast_interval_ms must be positive!");
+        }
+        if acceleration.max_multiplier < 1 {
+            bail!("max_multiplier must be at least 1!");
+        }
+        Ok(Self {
+            fast_interval: Duration::from_millis(
+                acceleration.fast_interval_ms as u64,
+            ),
+            max_multiplier: acceleration.max_multiplier,
+        })
+    }
+}
+
+impl Acceleration {
+    fn multiplier(&self, interval: Duration) -> i32 {
+        if interval.is_zero() {
+            return self.max_multiplier;
+        }
+        let multiplier =
+            self.fast_interval.as_secs_f64() / interval.as_secs_f64();
+        (multiplier as i32).clamp(1, self.max_multiplier)
+    }
+}
+
+impl Display for Acceleration {
+    fn fmt(&self, formatter: &mut Formatter) -> FmtResult {
+        formatter.write_fmt(format_args!(
+            "{{fast interval: {:?}, max multiplier: {}}}",
+            self.fast_interval, self.max_multiplier
+        ))
+    }
+}
+
+#[derive(Default)]
+struct EncoderState {
+    input_states: [bool; 2],
+    // Quadrature transitions since the last detent. Positive for
+    // incrementing.
+    transitions: i32,
+    // Positive for incrementing.
+    last_detent: Option<(i32, Instant)>,
+    // Taps that haven't been sent yet. Positive for incrementing.
+    pending_taps: i32,
+    last_tap_time: Option<Instant>,
+}
+
+pub(crate) struct RotaryEncoderRemapper {
+    encoding: Encoding,
+    transitions_per_detent: i32,
+    increment_key_code: c_char,
+    decrement_key_code: c_char,
+    acceleration: Option<Acceleration>,
+    min_tap_interval: Duration,
+    state: RefCell<EncoderState>,
+}
+
+impl TryFrom<&RotaryEncoder> for RotaryEncoderRemapper {
+    type Error = anyhow::Error;
+
+    fn try_from(input: &RotaryEncoder) -> Result<Self, Self::Error> {
+        if input.inputs.len() != 2 {
+            bail!(
+                "Number of inputs ({}) provided is not 2",
+                input.inputs.len()
+            );
+        }
+        let transitions_per_detent = match input.transitions_per_detent {
+            0 => DEFAULT_TRANSITIONS_PER_DETENT,
+            value if value < 0 => {
+                bail!("transitions_per_detent must be non-negative!")
+            }
+            value => value,
+        };
+        if input.min_tap_interval_ms < 0 {
+            bail!("min_tap_interval_ms must be non-negative!");
+        }
+        let key_codes = convert_key_codes(&[
+            input.increment_key_code,
+            input.decrement_key_code,
+        ])?;
+        Ok(Self {
+            encoding: input.encoding.enum_value_or_default(),
+            transitions_per_detent,
+            increment_key_code: key_codes[0],
+            decrement_key_code: key_codes[1],
+            acceleration: input
+                .acceleration
+                .as_ref()
+                .map(Acceleration::try_from)
+                .transpose()?,
+            min_tap_interval: Duration::from_millis(
+                input.min_tap_interval_ms as u64,
+            ),
+            state: RefCell::new(Default::default()),
+        })
+    }
+}
+
+impl RotaryEncoderRemapper {
+    /// Returns the direction of the detent that has just completed, if any.
+    fn decode(
+        &self,
+        state: &mut EncoderState,
+        input_index: usize,
+        is_pressed: bool,
+    ) -> Option<i32> {
+        let last_input_states = state.input_states;
+        state.input_states[input_index] = is_pressed;
+        if last_input_states == state.input_states {
+            return None;
+        }
+        match self.encoding {
+            // Only count presses, since each detent is a press followed by a
+            // release of the same button.
+            Encoding::PULSE => match (input_index, is_pressed) {
+                (0, true) => Some(1),
+                (1, true) => Some(-1),
+                _ => None,
+            },
+            Encoding::QUADRATURE => {
+                let last_position = quadrature_position(last_input_states);
+                let position = quadrature_position(state.input_states);
+                match (position + 4 - last_position) % 4 {
+                    1 => state.transitions += 1,
+                    3 => state.transitions -= 1,
+                    // Both channels changed at once, so the direction is
+                    // unknown.
+                    _ => return None,
+                }
+                if state.transitions.abs() < self.transitions_per_detent {
+                    return None;
+                }
+                let direction = state.transitions.signum();
+                state.transitions -= direction * self.transitions_per_detent;
+                Some(direction)
+            }
+        }
+    }
+
+    fn add_detent(&self, state: &mut EncoderState, direction: i32) {
+        let now = Instant::now();
+        let multiplier = match (state.last_detent, self.acceleration.as_ref()) {
+            (Some((last_direction, last_time)), Some(acceleration))
+                if last_direction == direction =>
+            {
+                acceleration
+                    .multiplier(now.saturating_duration_since(last_time))
+            }
+            _ => 1,
+        };
+        state.last_detent = Some((direction, now));
+        // Drop the queued taps if the direction is reversed, since the user
+        // probably went too far.
+        if state.pending_taps.signum() == -direction {
+            state.pending_taps = 0;
+        }
+        state.pending_taps += direction * multiplier;
+    }
+
+    fn send_next_tap(
+        &self,
+        state: &mut EncoderState,
+        now: Instant,
+    ) -> Vec<KeyEvent> {
+        if state.pending_taps == 0 {
+            return vec![];
+        }
+        if let Some(last_tap_time) = state.last_tap_time {
+            if now.saturating_duration_since(last_tap_time)
+                < self.min_tap_interval
+            {
+                return vec![];
+            }
+        }
+        state.last_tap_time = Some(now);
+        let key_code = if state.pending_taps > 0 {
+            state.pending_taps -= 1;
+            self.increment_key_code
+        } else {
+            state.pending_taps += 1;
+            self.decrement_key_code
+        };
+        match key_code {
+            0 => vec![],
+            key_code => vec![KeyEvent::PressAndRelease(key_code)],
+        }
+    }
+}
+
+impl RemapMultiInputValue for RotaryEncoderRemapper {
+    fn remap(&self, input_index: usize, value: i32) -> Vec<KeyEvent> {
+        let mut state = self.state.borrow_mut();
+        if let Some(direction) =
+            self.decode(&mut state, input_index, value != 0)
+        {
+            self.add_detent(&mut state, direction);
+        }
+        self.send_next_tap(&mut state, Instant::now())
+    }
+
+    fn tick(&self, now: Instant) -> Vec<KeyEvent> {
+        self.send_next_tap(&mut self.state.borrow_mut(), now)
+    }
+}
+
+impl Display for RotaryEncoderRemapper {
+    fn fmt(&self, formatter: &mut Formatter) -> FmtResult {
+        formatter.write_fmt(format_args!(
+            "{{increment: {}, decrement: {}, encoding: {:?}",
+            self.increment_key_code, self.decrement_key_code, self.encoding
+        ))?;
+        if self.encoding == Encoding::QUADRATURE {
+            formatter.write_fmt(format_args!(
+                ", transitions per detent: {}",
+                self.transitions_per_detent
+            ))?;
+        }
+        if let Some(acceleration) = self.acceleration.as_ref() {
+            formatter
+                .write_fmt(format_args!(", acceleration: {}", acceleration))?;
+        }
+        formatter.write_fmt(format_args!(
+            ", min tap interval: {:?}}}",
+            self.min_tap_interval
+        ))
+    }
+}
+
+fn quadrature_position(input_states: [bool; 2]) -> usize {
+    let state = (input_states[0] as usize) << 1 | input_states[1] as usize;
+    QUADRATURE_SEQUENCE
+        .iter()
+        .position(|&sequence_state| sequence_state == state)
+        .unwrap()
+}
@@ -28,13 +28,13 @@ impl TryFrom<&ToggleSwitchInput> for ToggleSwitchRemapper {
 }
 
 impl RemapInputValue for ToggleSwitchRemapper {
-    fn remap(&self, value: i32) -> Option<KeyEvent> {
+    fn remap(&self, value: i32) -> Vec<KeyEvent> {
         let key_code = if value != 0 {
             self.on_key_code
         } else {
             self.off_key_code
         };
-        Some(KeyEvent::PressAndRelease(key_code))
+        vec![KeyEvent::PressAndRelease(key_code)]
     }
 }
 
@@ -0,0 +1,119 @@
+use std::cell::RefCell;
+use std::convert::TryFrom;
+use std::f64::consts::FRAC_PI_2;
+use std::f64::consts::FRAC_PI_4;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::time::Instant;
+
+use anyhow::anyhow;
+use anyhow::bail;
+
+use super::axis_deflection::AxisDeflection;
+use super::hat_switch_remapper::HatSwitchRemapper;
+use super::RemapInputValue;
+use super::RemapMultiInputValue;
+use crate::input_reader::hid_device_input::HAT_SWITCH_CENTER;
+use crate::input_remapping::hat_switch_input::DiagonalMode;
+use crate::input_remapping::VirtualHatSwitch;
+use crate::virtual_device::KeyEvent;
+
+/// Converts the deflection of two axes to hat switch positions, and leaves the
+/// rest to `HatSwitchRemapper`.
+pub(crate) struct VirtualHatSwitchRemapper {
+    x_axis: AxisDeflection,
+    y_axis: AxisDeflection,
+    deadzone: f64,
+    is_eight_way: bool,
+    hat_switch_remapper: HatSwitchRemapper,
+    // The deflection of the horizontal and the vertical axis.
+    deflection: RefCell<[f64; 2]>,
+}
+
+impl TryFrom<&VirtualHatSwitch> for VirtualHatSwitchRemapper {
+    type Error = anyhow::Error;
+
+    fn try_from(input: &VirtualHatSwitch) -> Result<Self, Self::Error> {
+        if input.inputs.len() != 2 {
+            bail!(
+                "Number of inputs ({}) provided is not 2",
+                input.inputs.len()
+            );
+        }
+        if !(0.0..1.0).contains(&input.deadzone) {
+            bail!("deadzone must be in [0, 1)!");
+        }
+        let hat_switch_input = input
+            .hat_switch_input
+            .as_ref()
+            .ok_or_else(|| anyhow!("hat_switch_input is not provided!"))?;
+        Ok(Self {
+            // The radial deadzone is applied after combining both axes.
+            x_axis: AxisDeflection::new(
+                input.min_value,
+                input.max_value,
+                input.reverse_x_axis,
+                /* deadzone= */ 0.0,
+            )?,
+            y_axis: AxisDeflection::new(
+                input.min_value,
+                input.max_value,
+                input.reverse_y_axis,
+                /* deadzone= */ 0.0,
+            )?,
+            deadzone: input.deadzone as f64,
+            is_eight_way: hat_switch_input.key_codes.len() == 8
+                || hat_switch_input.diagonal_mode.enum_value_or_default()
+                    == DiagonalMode::COMBINED_KEYS,
+            hat_switch_remapper: HatSwitchRemapper::try_from(hat_switch_input)?,
+            deflection: RefCell::new([0.0, 0.0]),
+        })
+    }
+}
+
+impl VirtualHatSwitchRemapper {
+    /// Returns the hat switch position in the same format as the input reader
+    /// reports for real hat switches, i.e. clockwise from 0 for up.
+    fn get_hat_position(&self) -> i32 {
+        let [x, y] = *self.deflection.borrow();
+        if x.hypot(y) <= self.deadzone {
+            return HAT_SWITCH_CENTER;
+        }
+        // The angle is clockwise from up, since positive y is down.
+        let angle = x.atan2(-y);
+        if self.is_eight_way {
+            (angle / FRAC_PI_4).round().rem_euclid(8.0) as i32
+        } else {
+            (angle / FRAC_PI_2).round().rem_euclid(4.0) as i32 * 2
+        }
+    }
+}
+
+impl RemapMultiInputValue for VirtualHatSwitchRemapper {
+    fn remap(&self, input_index: usize, value: i32) -> Vec<KeyEvent> {
+        let axis = match input_index {
+            0 => &self.x_axis,
+            _ => &self.y_axis,
+        };
+        self.deflection.borrow_mut()[input_index] = axis.deflection(value);
+        self.hat_switch_remapper.remap(self.get_hat_position())
+    }
+
+    fn tick(&self, now: Instant) -> Vec<KeyEvent> {
+        self.hat_switch_remapper.tick(now)
+    }
+}
+
+impl Display for VirtualHatSwitchRemapper {
+    fn fmt(&self, formatter: &mut Formatter) -> FmtResult {
+        formatter.write_fmt(format_args!(
+            "{{x axis: {}, y axis: {}, deadzone: {}, {}-way hat: {}}}",
+            self.x_axis,
+            self.y_axis,
+            self.deadzone,
+            if self.is_eight_way { 8 } else { 4 },
+            self.hat_switch_remapper
+        ))
+    }
+}
@@ -6,6 +6,8 @@ include!(concat!(env!("OUT_DIR"), "/protos/mod.rs"));
 mod device_manager;
 mod input_reader;
 mod input_remapper;
+mod run_loop_timer;
+mod token_bucket;
 pub(crate) mod utils;
 mod virtual_device;
 
@@ -69,6 +71,20 @@ pub unsafe extern "C" fn LoadInputRemapping(
         .is_ok()
 }
 
+/// Returns how many key presses were dropped by rate limiting, or 0 if
+/// `manager_ptr` is null. Presses dropped by the `rate_limit` of inputs are
+/// only counted since the input remapping was loaded. The caller must pass in
+/// the pointer returned by `OpenLib()`.
+#[no_mangle]
+pub unsafe extern "C" fn GetNumSuppressedKeyPresses(
+    manager_ptr: *mut c_void,
+) -> u64 {
+    match (manager_ptr as *const DeviceManager).as_ref() {
+        Some(manager) => manager.num_suppressed_key_presses(),
+        None => 0,
+    }
+}
+
 /// The caller must pass in the pointer returned by `OpenLib()`.
 #[no_mangle]
 pub unsafe extern "C" fn CloseLib(manager_ptr: *mut c_void) {
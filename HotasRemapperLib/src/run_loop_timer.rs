@@ -0,0 +1,82 @@
+use std::cell::RefCell;
+use std::ffi::c_void;
+use std::time::Duration;
+
+use core_foundation::base::TCFType;
+use core_foundation::date::CFAbsoluteTimeGetCurrent;
+use core_foundation::runloop::kCFRunLoopDefaultMode;
+use core_foundation::runloop::CFRunLoop;
+use core_foundation::runloop::CFRunLoopTimer;
+use core_foundation::runloop::CFRunLoopTimerCallBack;
+use core_foundation::runloop::CFRunLoopTimerContext;
+use core_foundation::runloop::CFRunLoopTimerInvalidate;
+
+/// A trait to provide what we need for calling `CFRunLoopTimerCreate()`.
+pub(crate) trait HandleTimerEvent {
+    fn timer_fired_callback() -> CFRunLoopTimerCallBack;
+}
+
+/// A struct wrapping `CFRunLoopTimer` from Core Foundation. Once started, the
+/// timer fires repeatedly on the current run loop, which is the same run loop
+/// that delivers HID events, so the handler doesn't need any synchronization.
+pub(crate) struct RunLoopTimer {
+    interval: Duration,
+    timer: RefCell<Option<CFRunLoopTimer>>,
+}
+
+impl RunLoopTimer {
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            timer: RefCell::new(None),
+        }
+    }
+
+    /// Safety: the caller must ensure the pinned handler outlives
+    /// `RunLoopTimer`.
+    #[deny(unsafe_op_in_unsafe_fn)]
+    pub unsafe fn start<T: HandleTimerEvent>(
+        &self,
+        pinned_handler_ptr: *const T,
+    ) {
+        self.stop();
+        // The context is copied by `CFRunLoopTimerCreate()`, so it doesn't
+        // need to outlive this function call.
+        let mut context = CFRunLoopTimerContext {
+            version: 0,
+            info: pinned_handler_ptr as *mut c_void,
+            retain: None,
+            release: None,
+            copyDescription: None,
+        };
+        let interval = self.interval.as_secs_f64();
+        // Trivially safe.
+        let fire_date = unsafe { CFAbsoluteTimeGetCurrent() } + interval;
+        let timer = CFRunLoopTimer::new(
+            fire_date,
+            interval,
+            /* flags= */ 0,
+            /* order= */ 0,
+            T::timer_fired_callback(),
+            &mut context,
+        );
+        // Safe because `kCFRunLoopDefaultMode` is a static string.
+        CFRunLoop::get_current()
+            .add_timer(&timer, unsafe { kCFRunLoopDefaultMode });
+        self.timer.replace(Some(timer));
+    }
+
+    pub fn stop(&self) {
+        if let Some(timer) = self.timer.take() {
+            // Safe because `timer` is still alive.
+            unsafe { CFRunLoopTimerInvalidate(timer.as_concrete_TypeRef()) };
+        }
+    }
+}
+
+impl Drop for RunLoopTimer {
+    fn drop(&mut self) {
+        // Prevent the callback to be invoked after dropping.
+        self.stop();
+    }
+}
@@ -0,0 +1,121 @@
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::hash::Hash;
+use std::time::Duration;
+use std::time::Instant;
+
+use anyhow::bail;
+
+use crate::input_remapping::RateLimit;
+
+/// Allows `burst` events at once, and one more every `refill_interval`. Keeps
+/// count of the events that are not allowed.
+#[derive(Clone)]
+pub(crate) struct TokenBucket {
+    burst: u32,
+    refill_interval: Duration,
+    tokens: u32,
+    last_refill_time: Option<Instant>,
+    num_suppressed: u64,
+}
+
+impl TryFrom<&RateLimit> for TokenBucket {
+    type Error = anyhow::Error;
+
+    fn try_from(rate_limit: &RateLimit) -> Result<Self, Self::Error> {
+        if rate_limit.burst <= 0 {
+            bail!("burst must be positive!");
+        }
+        if rate_limit.refill_interval_ms <= 0 {
+            bail!("refill_interval_ms must be positive!");
+        }
+        Ok(Self {
+            burst: rate_limit.burst as u32,
+            refill_interval: Duration::from_millis(
+                rate_limit.refill_interval_ms as u64,
+            ),
+            tokens: rate_limit.burst as u32,
+            last_refill_time: None,
+            num_suppressed: 0,
+        })
+    }
+}
+
+impl TokenBucket {
+    /// Returns whether the event is allowed.
+    pub fn try_take(&mut self, now: Instant) -> bool {
+        self.refill(now);
+        if self.tokens == 0 {
+            self.num_suppressed += 1;
+            return false;
+        }
+        self.tokens -= 1;
+        true
+    }
+
+    pub fn num_suppressed(&self) -> u64 {
+        self.num_suppressed
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let Some(last_refill_time) = self.last_refill_time else {
+            self.last_refill_time = Some(now);
+            return;
+        };
+        let num_refills =
+            (now.saturating_duration_since(last_refill_time).as_nanos()
+                / self.refill_interval.as_nanos()) as u32;
+        self.tokens = self.tokens.saturating_add(num_refills).min(self.burst);
+        // A full bucket doesn't accumulate time for later refills.
+        self.last_refill_time = Some(if self.tokens == self.burst {
+            now
+        } else {
+            last_refill_time + self.refill_interval * num_refills
+        });
+    }
+}
+
+impl Display for TokenBucket {
+    fn fmt(&self, formatter: &mut Formatter) -> FmtResult {
+        formatter.write_fmt(format_args!(
+            "{{burst: {}, refill interval: {:?}}}",
+            self.burst, self.refill_interval
+        ))
+    }
+}
+
+/// One `TokenBucket` for each key, created when the key is first seen.
+pub(crate) struct KeyedTokenBuckets<K> {
+    initial_bucket: TokenBucket,
+    buckets: HashMap<K, TokenBucket>,
+}
+
+impl<K: Eq + Hash> KeyedTokenBuckets<K> {
+    pub fn new(initial_bucket: TokenBucket) -> Self {
+        Self {
+            initial_bucket,
+            buckets: Default::default(),
+        }
+    }
+
+    /// Returns whether the event for `key` is allowed.
+    pub fn try_take(&mut self, key: K, now: Instant) -> bool {
+        self.buckets
+            .entry(key)
+            .or_insert_with(|| self.initial_bucket.clone())
+            .try_take(now)
+    }
+
+    pub fn num_suppressed(&self) -> u64 {
+        self.buckets.values().map(TokenBucket::num_suppressed).sum()
+    }
+}
+
+impl<K> Display for KeyedTokenBuckets<K> {
+    fn fmt(&self, formatter: &mut Formatter) -> FmtResult {
+        self.initial_bucket.fmt(formatter)
+    }
+}
@@ -90,6 +90,10 @@ impl BluetoothDevice {
         self.is_rfcomm_channel_opened = is_opened;
     }
 
+    pub fn is_rfcomm_channel_opened(&self) -> bool {
+        self.is_rfcomm_channel_opened
+    }
+
     pub fn send_data(&self, data: &[c_char]) {
         if !self.is_rfcomm_channel_opened {
             return;
@@ -43,6 +43,8 @@ pub(crate) struct BluetoothManager<T: SelectDevice> {
     target_device: RefCell<Option<BluetoothDevice>>,
     rfcomm_channel_id: u8,
     connection_status_callback: ConnectionStatusCallback,
+    // Sent whenever the RFCOMM channel is opened.
+    initial_frame: Vec<c_char>,
     // We want to make sure the `BluetoothManager` doesn't get moved, so
     // callback functions can rely on an everlasting pointer to it.
     _pinned_marker: PhantomPinned,
@@ -53,6 +55,7 @@ impl<T: SelectDevice> BluetoothManager<T> {
         target_device_selector: T,
         rfcomm_channel_id: u8,
         connection_status_callback: ConnectionStatusCallback,
+        initial_frame: Vec<c_char>,
     ) -> Pin<Box<Self>> {
         static REGISTER_CLASS: Once = Once::new();
         REGISTER_CLASS.call_once(Self::register_class);
@@ -62,6 +65,7 @@ impl<T: SelectDevice> BluetoothManager<T> {
             target_device: RefCell::new(None),
             rfcomm_channel_id,
             connection_status_callback,
+            initial_frame,
             _pinned_marker: PhantomPinned,
         });
         unsafe { manager.as_mut().store_self_pointer() };
@@ -69,6 +73,15 @@ impl<T: SelectDevice> BluetoothManager<T> {
         manager
     }
 
+    /// Returns whether the RFCOMM channel to the target device is opened, i.e.
+    /// whether data can be sent to it.
+    pub fn is_target_device_connected(&self) -> bool {
+        self.target_device
+            .borrow()
+            .as_ref()
+            .is_some_and(BluetoothDevice::is_rfcomm_channel_opened)
+    }
+
     pub fn send_data_to_target_device(&self, data: &[c_char]) {
         if let Some(device) = self.target_device.borrow().as_ref() {
             device.send_data(data);
@@ -169,7 +182,8 @@ impl<T: SelectDevice> BluetoothManager<T> {
         );
         match self.target_device.borrow_mut().as_mut() {
             Some(device) => {
-                device.update_rfcomm_channel_status(/* is_opened= */ true)
+                device.update_rfcomm_channel_status(/* is_opened= */ true);
+                device.send_data(&self.initial_frame);
             }
             None => println!("Target device is None!"),
         }
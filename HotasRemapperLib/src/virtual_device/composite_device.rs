@@ -0,0 +1,219 @@
+use std::ffi::c_char;
+
+use anyhow::bail;
+use anyhow::Result;
+
+use super::consumer_control_input_report;
+use super::gamepad_input_report;
+use super::keyboard_input_report;
+use super::mouse_input_report;
+use super::nkro_keyboard_input_report;
+use super::report_descriptor::find_collection_end;
+use super::report_descriptor::get_input_report_length;
+use crate::settings::virtual_device_settings::KeyboardReportFormat;
+
+/// The kinds of output that the virtual device can emit. Each kind has its own
+/// report, and the report ID is fixed no matter which kinds are enabled.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub(crate) enum OutputKind {
+    Keyboard = 1,
+    Mouse = 2,
+    Gamepad = 3,
+    ConsumerControl = 4,
+}
+
+/// The report ID of the device configuration, which is not an actual report
+/// but tells the Raspberry Pi how to set up the HID device. It's sent whenever
+/// the link is established, so that the host and the device always agree.
+pub(crate) const DEVICE_CONFIG_REPORT_ID: u8 = 0xFF;
+
+/// A report along with its report ID, which is 0 if the device doesn't use
+/// report IDs.
+pub(crate) struct TaggedReport<'a> {
+    pub report_id: u8,
+    pub report: &'a [c_char],
+}
+
+impl TaggedReport<'_> {
+    /// Each report is sent in a frame that starts with the report ID and the
+    /// length of the report, so that the Raspberry Pi can tell reports apart.
+    pub fn to_frame(&self) -> Vec<c_char> {
+        let mut frame = Vec::with_capacity(self.report.len() + 2);
+        frame.push(self.report_id as c_char);
+        frame.push(self.report.len() as c_char);
+        frame.extend_from_slice(self.report);
+        frame
+    }
+}
+
+/// The HID device set up on the Raspberry Pi, which combines the reports of all
+/// enabled output kinds. The keyboard is always enabled. If it's the only one,
+/// report IDs are not used, so that the keyboard still works with hosts that
+/// only speak the boot protocol.
+pub(crate) struct CompositeDevice {
+    keyboard_report_format: KeyboardReportFormat,
+    output_kinds: Vec<OutputKind>,
+    report_descriptor: Vec<u8>,
+}
+
+impl CompositeDevice {
+    pub fn new(
+        keyboard_report_format: KeyboardReportFormat,
+        additional_output_kinds: &[OutputKind],
+    ) -> Result<Self> {
+        let mut output_kinds = vec![OutputKind::Keyboard];
+        for &output_kind in additional_output_kinds {
+            if !output_kinds.contains(&output_kind) {
+                output_kinds.push(output_kind);
+            }
+        }
+        output_kinds.sort_by_key(|&output_kind| output_kind as u8);
+        let mut device = Self {
+            keyboard_report_format,
+            output_kinds,
+            report_descriptor: vec![],
+        };
+        for &output_kind in device.output_kinds.iter() {
+            let descriptor = device.get_report_descriptor(output_kind);
+            let report_length = device.get_report_length(output_kind);
+            let described_report_length = get_input_report_length(descriptor);
+            if report_length != described_report_length {
+                bail!(
+                    "{:?} reports have {} bytes, but the report descriptor \
+                    describes {} bytes",
+                    output_kind,
+                    report_length,
+                    described_report_length
+                );
+            }
+        }
+        device.report_descriptor = device.build_report_descriptor()?;
+        if device.device_config().len() > u8::MAX as usize {
+            bail!("Report descriptor is too long to be sent");
+        }
+        Ok(device)
+    }
+
+    pub fn output_kinds(&self) -> &[OutputKind] {
+        &self.output_kinds
+    }
+
+    /// Returns `None` if `output_kind` is not enabled.
+    pub fn tag_report<'a>(
+        &self,
+        output_kind: OutputKind,
+        report: &'a [c_char],
+    ) -> Option<TaggedReport<'a>> {
+        if !self.output_kinds.contains(&output_kind) {
+            return None;
+        }
+        Some(TaggedReport {
+            report_id: if self.uses_report_ids() {
+                output_kind as u8
+            } else {
+                0
+            },
+            report,
+        })
+    }
+
+    /// Returns the report descriptors of all enabled output kinds
+    /// concatenated, with report IDs inserted if needed.
+    pub fn report_descriptor(&self) -> &[u8] {
+        &self.report_descriptor
+    }
+
+    /// Returns the length of the longest report, including the report ID.
+    pub fn max_report_length(&self) -> usize {
+        let max_report_length = self
+            .output_kinds
+            .iter()
+            .map(|&output_kind| {
+                get_input_report_length(self.get_report_descriptor(output_kind))
+            })
+            .max()
+            .unwrap_or_default();
+        if self.uses_report_ids() {
+            max_report_length + 1
+        } else {
+            max_report_length
+        }
+    }
+
+    /// Returns whether the device can be a boot protocol keyboard.
+    pub fn is_boot_keyboard(&self) -> bool {
+        !self.uses_report_ids()
+            && self.keyboard_report_format == KeyboardReportFormat::BOOT
+    }
+
+    /// Returns a byte telling whether the device is a boot protocol keyboard,
+    /// a byte for `max_report_length()`, and then the report descriptor.
+    pub fn device_config(&self) -> Vec<c_char> {
+        let mut device_config = vec![
+            self.is_boot_keyboard() as c_char,
+            self.max_report_length() as c_char,
+        ];
+        device_config
+            .extend(self.report_descriptor.iter().map(|&byte| byte as c_char));
+        device_config
+    }
+
+    fn build_report_descriptor(&self) -> Result<Vec<u8>> {
+        let mut report_descriptor = vec![];
+        for &output_kind in self.output_kinds.iter() {
+            let descriptor = self.get_report_descriptor(output_kind);
+            let Some(collection_end) = find_collection_end(descriptor) else {
+                bail!("{:?} report descriptor has no collection", output_kind);
+            };
+            if !self.uses_report_ids() {
+                report_descriptor.extend_from_slice(descriptor);
+                continue;
+            }
+            // The report ID item goes right into the top-level collection.
+            report_descriptor.extend_from_slice(&descriptor[..collection_end]);
+            report_descriptor.extend_from_slice(&[0x85, output_kind as u8]);
+            report_descriptor.extend_from_slice(&descriptor[collection_end..]);
+        }
+        Ok(report_descriptor)
+    }
+
+    fn uses_report_ids(&self) -> bool {
+        self.output_kinds.len() > 1
+    }
+
+    fn get_report_length(&self, output_kind: OutputKind) -> usize {
+        match output_kind {
+            OutputKind::Keyboard => match self.keyboard_report_format {
+                KeyboardReportFormat::BOOT => {
+                    keyboard_input_report::REPORT_LENGTH
+                }
+                KeyboardReportFormat::NKRO => {
+                    nkro_keyboard_input_report::REPORT_LENGTH
+                }
+            },
+            OutputKind::Mouse => mouse_input_report::REPORT_LENGTH,
+            OutputKind::Gamepad => gamepad_input_report::REPORT_LENGTH,
+            OutputKind::ConsumerControl => {
+                consumer_control_input_report::REPORT_LENGTH
+            }
+        }
+    }
+
+    fn get_report_descriptor(&self, output_kind: OutputKind) -> &'static [u8] {
+        match output_kind {
+            OutputKind::Keyboard => match self.keyboard_report_format {
+                KeyboardReportFormat::BOOT => {
+                    keyboard_input_report::REPORT_DESCRIPTOR
+                }
+                KeyboardReportFormat::NKRO => {
+                    nkro_keyboard_input_report::REPORT_DESCRIPTOR
+                }
+            },
+            OutputKind::Mouse => mouse_input_report::REPORT_DESCRIPTOR,
+            OutputKind::Gamepad => gamepad_input_report::REPORT_DESCRIPTOR,
+            OutputKind::ConsumerControl => {
+                consumer_control_input_report::REPORT_DESCRIPTOR
+            }
+        }
+    }
+}
@@ -0,0 +1,86 @@
+use std::ffi::c_char;
+
+const NUM_USAGE_SLOTS: usize = 2;
+
+pub(crate) const REPORT_LENGTH: usize = NUM_USAGE_SLOTS * 2;
+
+// The largest usage that the report descriptor set up on the Raspberry Pi
+// accepts, which covers the common media and application control usages.
+pub(crate) const MAX_USAGE: u16 = 0x3FF;
+
+#[rustfmt::skip]
+pub(crate) const REPORT_DESCRIPTOR: &[u8] = &[
+    0x05, 0x0C,       // Usage Page (Consumer)
+    0x09, 0x01,       // Usage (Consumer Control)
+    0xA1, 0x01,       // Collection (Application)
+    0x15, 0x00,       //   Logical Minimum (0)
+    0x26, 0xFF, 0x03, //   Logical Maximum (0x3FF)
+    0x19, 0x00,       //   Usage Minimum (0)
+    0x2A, 0xFF, 0x03, //   Usage Maximum (0x3FF)
+    0x75, 0x10,       //   Report Size (16)
+    0x95, 0x02,       //   Report Count (2)
+    0x81, 0x00,       //   Input (Data, Array, Absolute)
+    0xC0,             // End Collection
+];
+
+/// The consumer control report, with two slots for the usages (on the consumer
+/// page) that are currently pressed, each taking 2 bytes (little-endian).
+/// Its layout must match the report descriptor set up on the Raspberry Pi.
+pub(crate) struct ConsumerControlInputReport {
+    usage_slots: [u16; NUM_USAGE_SLOTS],
+}
+
+impl ConsumerControlInputReport {
+    pub fn new() -> Self {
+        Self {
+            usage_slots: [0; NUM_USAGE_SLOTS],
+        }
+    }
+
+    /// Returns whether the report has changed.
+    pub fn update_usage_state(&mut self, usage: u16, is_pressed: bool) -> bool {
+        let (slot_usage, new_usage) =
+            if is_pressed { (0, usage) } else { (usage, 0) };
+        if is_pressed && self.usage_slots.contains(&usage) {
+            return false;
+        }
+        match self
+            .usage_slots
+            .iter_mut()
+            .find(|slot| **slot == slot_usage)
+        {
+            Some(slot) => {
+                *slot = new_usage;
+                true
+            }
+            None => {
+                if is_pressed {
+                    println!(
+                        "No available slot for consumer control usage press, \
+                        dropped (usage: {:#x})",
+                        usage
+                    );
+                }
+                false
+            }
+        }
+    }
+
+    /// Returns whether any usage was pressed.
+    pub fn release_all_usages(&mut self) -> bool {
+        let has_pressed_usages =
+            self.usage_slots.iter().any(|&usage| usage != 0);
+        self.usage_slots.fill(0);
+        has_pressed_usages
+    }
+
+    pub fn report(&self) -> [c_char; REPORT_LENGTH] {
+        let mut report = [0; REPORT_LENGTH];
+        for (index, usage) in self.usage_slots.iter().enumerate() {
+            for (offset, byte) in usage.to_le_bytes().into_iter().enumerate() {
+                report[index * 2 + offset] = byte as c_char;
+            }
+        }
+        report
+    }
+}
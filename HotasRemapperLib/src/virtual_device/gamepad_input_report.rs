@@ -0,0 +1,167 @@
+use std::ffi::c_char;
+
+use super::GamepadAxis;
+
+enum ReportField {
+    ButtonStatesBegin = 0,
+    DpadState = 2,
+    SticksBegin = 3,
+    LeftTrigger = 11,
+    RightTrigger = 12,
+    EndExclusive = 13,
+}
+
+pub(crate) const REPORT_LENGTH: usize = ReportField::EndExclusive as usize;
+
+pub(crate) const NUM_BUTTONS: u8 = 16;
+
+// The d-pad reports positions 0 to 7 clockwise from up, and anything out of
+// that range as the center (i.e. the null state).
+const DPAD_CENTER: c_char = 8;
+
+const STICK_MAX_VALUE: f32 = i16::MAX as f32;
+const TRIGGER_MAX_VALUE: f32 = u8::MAX as f32;
+
+#[rustfmt::skip]
+pub(crate) const REPORT_DESCRIPTOR: &[u8] = &[
+    0x05, 0x01,       // Usage Page (Generic Desktop)
+    0x09, 0x05,       // Usage (Game Pad)
+    0xA1, 0x01,       // Collection (Application)
+    0x05, 0x09,       //   Usage Page (Button)
+    0x19, 0x01,       //   Usage Minimum (1)
+    0x29, 0x10,       //   Usage Maximum (16)
+    0x15, 0x00,       //   Logical Minimum (0)
+    0x25, 0x01,       //   Logical Maximum (1)
+    0x75, 0x01,       //   Report Size (1)
+    0x95, 0x10,       //   Report Count (16)
+    0x81, 0x02,       //   Input (Data, Variable, Absolute)
+    0x05, 0x01,       //   Usage Page (Generic Desktop)
+    0x09, 0x39,       //   Usage (Hat Switch)
+    0x15, 0x00,       //   Logical Minimum (0)
+    0x25, 0x07,       //   Logical Maximum (7)
+    0x35, 0x00,       //   Physical Minimum (0)
+    0x46, 0x3B, 0x01, //   Physical Maximum (315)
+    0x65, 0x14,       //   Unit (Degrees)
+    0x75, 0x04,       //   Report Size (4)
+    0x95, 0x01,       //   Report Count (1)
+    0x81, 0x42,       //   Input (Data, Variable, Absolute, Null State)
+    0x65, 0x00,       //   Unit (None)
+    0x45, 0x00,       //   Physical Maximum (0)
+    0x75, 0x04,       //   Report Size (4)
+    0x95, 0x01,       //   Report Count (1)
+    0x81, 0x01,       //   Input (Constant)
+    0x09, 0x30,       //   Usage (X)
+    0x09, 0x31,       //   Usage (Y)
+    0x09, 0x33,       //   Usage (Rx)
+    0x09, 0x34,       //   Usage (Ry)
+    0x16, 0x01, 0x80, //   Logical Minimum (-32767)
+    0x26, 0xFF, 0x7F, //   Logical Maximum (32767)
+    0x75, 0x10,       //   Report Size (16)
+    0x95, 0x04,       //   Report Count (4)
+    0x81, 0x02,       //   Input (Data, Variable, Absolute)
+    0x09, 0x32,       //   Usage (Z)
+    0x09, 0x35,       //   Usage (Rz)
+    0x15, 0x00,       //   Logical Minimum (0)
+    0x26, 0xFF, 0x00, //   Logical Maximum (255)
+    0x75, 0x08,       //   Report Size (8)
+    0x95, 0x02,       //   Report Count (2)
+    0x81, 0x02,       //   Input (Data, Variable, Absolute)
+    0xC0,             // End Collection
+];
+
+/// The gamepad report, with 2 bytes for 16 buttons, 1 byte for the d-pad, 2
+/// bytes for each axis of the sticks (signed, little-endian) and 1 byte for
+/// each trigger. Its layout must match the report descriptor set up on the
+/// Raspberry Pi.
+/// Unlike buttons, axes may change on every input event, so changes are
+/// recorded until the next report is taken rather than sent right away.
+pub(crate) struct GamepadInputReport {
+    report: [c_char; REPORT_LENGTH],
+    has_unsent_changes: bool,
+}
+
+impl GamepadInputReport {
+    pub fn new() -> Self {
+        let mut report = [0; REPORT_LENGTH];
+        report[ReportField::DpadState as usize] = DPAD_CENTER;
+        Self {
+            report,
+            has_unsent_changes: false,
+        }
+    }
+
+    pub fn update_button_state(&mut self, button: u8, is_pressed: bool) {
+        if button >= NUM_BUTTONS {
+            println!("Gamepad button out of range (button: {})", button);
+            return;
+        }
+        let button_states = &mut self.report
+            [ReportField::ButtonStatesBegin as usize + button as usize / 8];
+        let button_bit = 1 << (button % 8);
+        if is_pressed {
+            *button_states |= button_bit;
+        } else {
+            *button_states &= !button_bit;
+        }
+        self.has_unsent_changes = true;
+    }
+
+    /// `position` is the same as normalized hat switch values, i.e. 0 to 7
+    /// clockwise from up, or `None` for the center.
+    pub fn update_dpad_state(&mut self, position: Option<u8>) {
+        self.update_field(
+            ReportField::DpadState as usize,
+            position.map_or(DPAD_CENTER, |position| position as c_char),
+        );
+    }
+
+    /// `value` is in [-1.0, 1.0] for sticks, and [0.0, 1.0] for triggers.
+    pub fn update_axis_state(&mut self, axis: GamepadAxis, value: f32) {
+        match axis {
+            GamepadAxis::LeftTrigger | GamepadAxis::RightTrigger => {
+                let field = if axis == GamepadAxis::LeftTrigger {
+                    ReportField::LeftTrigger
+                } else {
+                    ReportField::RightTrigger
+                };
+                let value =
+                    (value.clamp(0.0, 1.0) * TRIGGER_MAX_VALUE).round() as u8;
+                self.update_field(field as usize, value as c_char);
+            }
+            _ => {
+                let value =
+                    (value.clamp(-1.0, 1.0) * STICK_MAX_VALUE).round() as i16;
+                let index =
+                    ReportField::SticksBegin as usize + axis as usize * 2;
+                for (offset, byte) in
+                    value.to_le_bytes().into_iter().enumerate()
+                {
+                    self.update_field(index + offset, byte as c_char);
+                }
+            }
+        }
+    }
+
+    pub fn has_unsent_changes(&self) -> bool {
+        self.has_unsent_changes
+    }
+
+    /// Returns whether the gamepad was not at rest.
+    pub fn reset(&mut self) -> bool {
+        let was_at_rest = self.report == Self::new().report;
+        *self = Self::new();
+        !was_at_rest
+    }
+
+    pub fn take_report(&mut self) -> [c_char; REPORT_LENGTH] {
+        self.has_unsent_changes = false;
+        self.report
+    }
+
+    fn update_field(&mut self, index: usize, value: c_char) {
+        if self.report[index] != value {
+            self.report[index] = value;
+            self.has_unsent_changes = true;
+        }
+    }
+}
@@ -0,0 +1,85 @@
+use std::collections::HashMap;
+use std::ffi::c_char;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::hash::Hash;
+
+use super::KeySource;
+
+/// Tracks the sources that hold each key, since several remapped inputs may
+/// share the same key. A key stays pressed in the input report until all of
+/// its sources have released it. Other kinds of buttons are tracked the same
+/// way, with `K` being their identifiers.
+pub(crate) struct KeyOwners<K = c_char> {
+    owners: HashMap<K, Vec<KeySource>>,
+}
+
+impl<K: Copy + Eq + Hash> KeyOwners<K> {
+    pub fn new() -> Self {
+        Self {
+            owners: Default::default(),
+        }
+    }
+
+    /// Returns whether the key should be pressed in the input report, i.e. it
+    /// wasn't held by any source.
+    pub fn press(&mut self, key_code: K, source: &KeySource) -> bool {
+        let owners = self.owners.entry(key_code).or_default();
+        let was_released = owners.is_empty();
+        if !owners.contains(source) {
+            owners.push(source.clone());
+        }
+        was_released
+    }
+
+    /// Returns whether the key should be released in the input report, i.e.
+    /// no source holds it anymore.
+    pub fn release(&mut self, key_code: K, source: &KeySource) -> bool {
+        let Some(owners) = self.owners.get_mut(&key_code) else {
+            return false;
+        };
+        owners.retain(|owner| owner != source);
+        // Other sources still hold it. They can be seen in the `Display`
+        // output if needed.
+        if !owners.is_empty() {
+            return false;
+        }
+        self.owners.remove(&key_code);
+        true
+    }
+
+    pub fn is_held(&self, key_code: K) -> bool {
+        self.owners.contains_key(&key_code)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.owners.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.owners.clear();
+    }
+}
+
+impl<K: Display> Display for KeyOwners<K> {
+    fn fmt(&self, formatter: &mut Formatter) -> FmtResult {
+        formatter.write_str("{")?;
+        for (index, (key_code, owners)) in self.owners.iter().enumerate() {
+            if index > 0 {
+                formatter.write_str(", ")?;
+            }
+            formatter.write_fmt(format_args!(
+                "{}: {}",
+                key_code,
+                format_sources(owners)
+            ))?;
+        }
+        formatter.write_str("}")
+    }
+}
+
+fn format_sources(sources: &[KeySource]) -> String {
+    let names: Vec<String> = sources.iter().map(KeySource::to_string).collect();
+    format!("[{}]", names.join(", "))
+}
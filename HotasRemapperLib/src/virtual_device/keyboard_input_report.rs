@@ -1,57 +1,152 @@
 use std::ffi::c_char;
+use std::ops::RangeInclusive;
 
 enum ReportField {
-    _ModifierKeyStates = 0,
+    ModifierKeyStates = 0,
     KeyStatesBegin = 1,
     KeyStatesEndExclusive = 7,
 }
 
-const REPORT_LENGTH: usize = ReportField::KeyStatesEndExclusive as usize;
+pub(crate) const REPORT_LENGTH: usize =
+    ReportField::KeyStatesEndExclusive as usize;
 
+// Usages of the left control key to the right GUI key, which are reported as
+// bits in the modifier byte rather than in the key slots.
+const MODIFIER_KEY_CODES: RangeInclusive<u8> = 0xE0..=0xE7;
+
+#[rustfmt::skip]
+pub(crate) const REPORT_DESCRIPTOR: &[u8] = &[
+    0x05, 0x01,       // Usage Page (Generic Desktop)
+    0x09, 0x06,       // Usage (Keyboard)
+    0xA1, 0x01,       // Collection (Application)
+    0x05, 0x07,       //   Usage Page (Keyboard)
+    0x19, 0xE0,       //   Usage Minimum (Left Control)
+    0x29, 0xE7,       //   Usage Maximum (Right GUI)
+    0x15, 0x00,       //   Logical Minimum (0)
+    0x25, 0x01,       //   Logical Maximum (1)
+    0x75, 0x01,       //   Report Size (1)
+    0x95, 0x08,       //   Report Count (8)
+    0x81, 0x02,       //   Input (Data, Variable, Absolute)
+    0x75, 0x08,       //   Report Size (8)
+    0x95, 0x06,       //   Report Count (6)
+    0x15, 0x00,       //   Logical Minimum (0)
+    0x26, 0xFF, 0x00, //   Logical Maximum (255)
+    0x19, 0x00,       //   Usage Minimum (0)
+    0x2A, 0xFF, 0x00, //   Usage Maximum (255)
+    0x81, 0x00,       //   Input (Data, Array, Absolute)
+    0xC0,             // End Collection
+];
+
+/// The input report sent to the target device. Its layout must match the
+/// report descriptor set up on the Raspberry Pi.
+pub(crate) trait KeyboardReport {
+    fn update_key_state(&mut self, key_code: c_char, is_pressed: bool);
+
+    /// Returns whether any key was pressed.
+    fn release_all_keys(&mut self) -> bool;
+
+    fn report(&self) -> &[c_char];
+}
+
+/// The boot protocol report, with one byte for modifier keys and six slots for
+/// other keys. Keys pressed while all slots are taken are queued, and take the
+/// slots freed by released keys.
 pub(crate) struct KeyboardInputReport {
     report: [c_char; REPORT_LENGTH],
+    overflowed_keys: Vec<c_char>,
 }
 
 impl KeyboardInputReport {
     pub fn new() -> Self {
         Self {
             report: [0; REPORT_LENGTH],
+            overflowed_keys: vec![],
         }
     }
 
-    pub fn report(&self) -> &[c_char; REPORT_LENGTH] {
-        &self.report
+    fn find_key_state(&mut self, key_code: c_char) -> Option<&mut c_char> {
+        for index in ReportField::KeyStatesBegin as usize
+            ..ReportField::KeyStatesEndExclusive as usize
+        {
+            if self.report[index] == key_code {
+                return Some(&mut self.report[index]);
+            }
+        }
+        None
     }
+}
 
-    pub fn update_key_state(&mut self, key_code: c_char, is_pressed: bool) {
+impl KeyboardReport for KeyboardInputReport {
+    fn update_key_state(&mut self, key_code: c_char, is_pressed: bool) {
+        if update_modifier_key_state(
+            &mut self.report[ReportField::ModifierKeyStates as usize],
+            key_code,
+            is_pressed,
+        ) {
+            return;
+        }
         // If this key has been pressed previously, remove it from the report if
-        // it is now released.
+        // it is now released, and give the slot to an overflowed key.
         if let Some(key_state) = self.find_key_state(key_code) {
             if !is_pressed {
                 *key_state = 0x00;
+                if !self.overflowed_keys.is_empty() {
+                    let overflowed_key = self.overflowed_keys.remove(0);
+                    self.update_key_state(
+                        overflowed_key,
+                        /* is_pressed= */ true,
+                    );
+                }
             }
             return;
         }
+        if !is_pressed {
+            self.overflowed_keys
+                .retain(|&overflowed| overflowed != key_code);
+            return;
+        }
         // If a new key is pressed, find an available slot for it.
-        if is_pressed {
-            match self.find_key_state(0x00) {
-                Some(key_state) => *key_state = key_code,
-                None => println!(
-                    "No available slot for key press (keycode: {})",
-                    key_code
-                ),
+        match self.find_key_state(0x00) {
+            Some(key_state) => *key_state = key_code,
+            None => {
+                if !self.overflowed_keys.contains(&key_code) {
+                    println!(
+                        "No available slot for key press, queued until a slot \
+                        is freed (keycode: {})",
+                        key_code
+                    );
+                    self.overflowed_keys.push(key_code);
+                }
             }
         }
     }
 
-    fn find_key_state(&mut self, key_code: c_char) -> Option<&mut c_char> {
-        for index in ReportField::KeyStatesBegin as usize
-            ..ReportField::KeyStatesEndExclusive as usize
-        {
-            if self.report[index] == key_code {
-                return Some(&mut self.report[index]);
-            }
-        }
-        None
+    fn release_all_keys(&mut self) -> bool {
+        let has_pressed_keys = self.report.iter().any(|&state| state != 0);
+        self.report.fill(0x00);
+        self.overflowed_keys.clear();
+        has_pressed_keys
+    }
+
+    fn report(&self) -> &[c_char] {
+        &self.report
+    }
+}
+
+/// Returns false if `key_code` is not a modifier key.
+pub(super) fn update_modifier_key_state(
+    modifier_key_states: &mut c_char,
+    key_code: c_char,
+    is_pressed: bool,
+) -> bool {
+    if !MODIFIER_KEY_CODES.contains(&(key_code as u8)) {
+        return false;
+    }
+    let modifier_bit = 1 << (key_code as u8 - MODIFIER_KEY_CODES.start());
+    if is_pressed {
+        *modifier_key_states |= modifier_bit;
+    } else {
+        *modifier_key_states &= !modifier_bit;
     }
+    true
 }
@@ -0,0 +1,29 @@
+use std::cell::RefCell;
+use std::ffi::c_char;
+use std::rc::Rc;
+
+use super::transport::Transport;
+
+/// Records the frames sent through it, so that tests can check the reports.
+/// Clones share the same frames. It's always connected.
+#[derive(Clone, Default)]
+pub(crate) struct MemoryTransport {
+    frames: Rc<RefCell<Vec<Vec<c_char>>>>,
+}
+
+impl MemoryTransport {
+    /// Returns the frames sent since the last call.
+    pub fn take_frames(&self) -> Vec<Vec<c_char>> {
+        self.frames.take()
+    }
+}
+
+impl Transport for MemoryTransport {
+    fn send_frame(&self, frame: &[c_char]) {
+        self.frames.borrow_mut().push(frame.to_vec());
+    }
+
+    fn is_connected(&self) -> bool {
+        true
+    }
+}
@@ -1,25 +1,59 @@
 mod bluetooth_device;
 mod bluetooth_manager;
+mod composite_device;
+mod consumer_control_input_report;
+mod gamepad_input_report;
+mod key_owners;
 mod keyboard_input_report;
+#[cfg(test)]
+mod memory_transport;
+mod mouse_input_report;
+mod network_transport;
+mod nkro_keyboard_input_report;
+mod output_scheduler;
+mod report_descriptor;
+mod rfcomm_transport;
+mod serial_transport;
+mod transport;
 
 use std::cell::RefCell;
 use std::ffi::c_char;
-use std::pin::Pin;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::rc::Rc;
+use std::time::Duration;
 use std::time::Instant;
 
 use anyhow::anyhow;
 use anyhow::bail;
 use anyhow::Result;
-use bluetooth_device::DeviceInfo;
-use bluetooth_manager::SelectDevice;
+use composite_device::CompositeDevice;
+pub(crate) use composite_device::OutputKind;
+use composite_device::TaggedReport;
+use composite_device::DEVICE_CONFIG_REPORT_ID;
+use consumer_control_input_report::ConsumerControlInputReport;
+pub(crate) use consumer_control_input_report::MAX_USAGE as MAX_CONSUMER_CONTROL_USAGE;
+use gamepad_input_report::GamepadInputReport;
+pub(crate) use gamepad_input_report::NUM_BUTTONS as NUM_GAMEPAD_BUTTONS;
+use key_owners::KeyOwners;
 use keyboard_input_report::KeyboardInputReport;
+use keyboard_input_report::KeyboardReport;
+use mouse_input_report::MouseInputReport;
+use nkro_keyboard_input_report::NkroKeyboardInputReport;
+use output_scheduler::OutputScheduler;
+use transport::create_transport;
+use transport::Transport;
+use transport::TransportContext;
 
+use crate::input_remapping::RateLimit;
+use crate::settings::virtual_device_settings::AdditionalOutput;
+use crate::settings::virtual_device_settings::KeyboardReportFormat;
 use crate::settings::VirtualDeviceSettings;
+use crate::token_bucket::KeyedTokenBuckets;
+use crate::token_bucket::TokenBucket;
 use crate::ConnectionStatusCallback;
 
-type BluetoothManager =
-    bluetooth_manager::BluetoothManager<VirtualDeviceSelector>;
-
 #[derive(Clone, Copy, Eq, PartialEq)]
 pub(crate) enum KeyEvent {
     Press(c_char),
@@ -31,36 +65,139 @@ pub(crate) enum KeyEvent {
     },
 }
 
-struct SentKeyEvent {
-    pub event: KeyEvent,
-    pub timestamp: Instant,
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum MouseAxis {
+    X = 0,
+    Y = 1,
+    Wheel = 2,
 }
 
-struct VirtualDeviceSelector {
-    mac_address: String,
+/// Mouse buttons are numbered from 0, i.e. 0 is the left button, 1 is the right
+/// button and 2 is the middle button.
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub(crate) enum MouseEvent {
+    Move { axis: MouseAxis, distance: i32 },
+    Press(u8),
+    Release(u8),
 }
 
-impl VirtualDeviceSelector {
-    pub fn new(settings: &VirtualDeviceSettings) -> Self {
-        Self {
-            mac_address: settings.mac_address.clone(),
-        }
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum GamepadAxis {
+    LeftStickX = 0,
+    LeftStickY = 1,
+    RightStickX = 2,
+    RightStickY = 3,
+    LeftTrigger = 4,
+    RightTrigger = 5,
+}
+
+/// Gamepad buttons are numbered from 0. Axis values are in [-1.0, 1.0] for
+/// sticks, and [0.0, 1.0] for triggers. The d-pad position is the same as
+/// normalized hat switch values, or `None` for the center.
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) enum GamepadEvent {
+    MoveAxis { axis: GamepadAxis, value: f32 },
+    Press(u8),
+    Release(u8),
+    MoveDpad(Option<u8>),
+}
+
+/// Usages on the consumer page, e.g. for volume control.
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub(crate) enum ConsumerControlEvent {
+    Press(u16),
+    Release(u16),
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) enum OutputEvent {
+    Key(KeyEvent),
+    Mouse(MouseEvent),
+    Gamepad(GamepadEvent),
+    ConsumerControl(ConsumerControlEvent),
+}
+
+impl From<KeyEvent> for OutputEvent {
+    fn from(key_event: KeyEvent) -> Self {
+        OutputEvent::Key(key_event)
+    }
+}
+
+impl From<MouseEvent> for OutputEvent {
+    fn from(mouse_event: MouseEvent) -> Self {
+        OutputEvent::Mouse(mouse_event)
+    }
+}
+
+impl From<GamepadEvent> for OutputEvent {
+    fn from(gamepad_event: GamepadEvent) -> Self {
+        OutputEvent::Gamepad(gamepad_event)
     }
 }
 
-impl SelectDevice for VirtualDeviceSelector {
-    fn is_target_device(&self, device_info: &DeviceInfo) -> bool {
-        device_info.mac_address == self.mac_address
+impl From<ConsumerControlEvent> for OutputEvent {
+    fn from(consumer_control_event: ConsumerControlEvent) -> Self {
+        OutputEvent::ConsumerControl(consumer_control_event)
     }
 }
 
-/// This device is connected via Bluetooth, responsible for forwarding HID
-/// keyboard input events generated by us.
+/// Names what fires key events, e.g. a remapped input, so that keys can be
+/// tracked by who holds them.
+#[derive(Clone, Eq, PartialEq)]
+pub(crate) struct KeySource(Rc<str>);
+
+impl KeySource {
+    pub fn new(name: String) -> Self {
+        Self(name.into())
+    }
+}
+
+impl Display for KeySource {
+    fn fmt(&self, formatter: &mut Formatter) -> FmtResult {
+        formatter.write_str(&self.0)
+    }
+}
+
+impl KeyEvent {
+    /// Returns the key that this event presses, if any.
+    pub fn pressed_key_code(&self) -> Option<c_char> {
+        match *self {
+            KeyEvent::Press(key_code) | KeyEvent::PressAndRelease(key_code) => {
+                Some(key_code)
+            }
+            KeyEvent::ReleaseAndPress { to_press, .. } => Some(to_press),
+            KeyEvent::Release(_) => None,
+        }
+    }
+
+    /// Returns what's left after dropping the key press of this event.
+    pub fn without_press(&self) -> Option<KeyEvent> {
+        match *self {
+            KeyEvent::Press(_) | KeyEvent::PressAndRelease(_) => None,
+            KeyEvent::ReleaseAndPress { to_release, .. } => {
+                Some(KeyEvent::Release(to_release))
+            }
+            KeyEvent::Release(_) => Some(*self),
+        }
+    }
+}
+
+/// This device is connected via a transport selected in the settings,
+/// responsible for forwarding HID keyboard, mouse, gamepad and consumer control
+/// input events generated by us.
 pub(crate) struct VirtualDevice {
-    bluetooth_manager: Pin<Box<BluetoothManager>>,
-    input_report: RefCell<KeyboardInputReport>,
-    last_sent_key_event: RefCell<Option<SentKeyEvent>>,
-    rate_limiting_threshold_ms: u128,
+    transport: Box<dyn Transport>,
+    composite_device: CompositeDevice,
+    input_report: RefCell<Box<dyn KeyboardReport>>,
+    key_owners: RefCell<KeyOwners>,
+    output_scheduler: RefCell<OutputScheduler>,
+    key_rate_limiter: Option<RefCell<KeyedTokenBuckets<c_char>>>,
+    mouse_report: RefCell<MouseInputReport>,
+    mouse_button_owners: RefCell<KeyOwners<u8>>,
+    gamepad_report: RefCell<GamepadInputReport>,
+    gamepad_button_owners: RefCell<KeyOwners<u8>>,
+    consumer_control_report: RefCell<ConsumerControlInputReport>,
+    consumer_control_usage_owners: RefCell<KeyOwners<u16>>,
 }
 
 impl VirtualDevice {
@@ -68,86 +205,509 @@ impl VirtualDevice {
         settings: &VirtualDeviceSettings,
         connection_status_callback: ConnectionStatusCallback,
     ) -> Result<Self> {
-        let rfcomm_channel_id = u8::try_from(settings.rfcomm_channel_id)
-            .map_err(|e| {
-                anyhow!("Cannot convert rfcomm_channel_id to u8: {}", e)
-            })?;
-        let rate_limiting_threshold_ms = settings.rate_limiting_threshold_ms;
-        if rate_limiting_threshold_ms < 0 {
-            bail!("rate_limiting_threshold_ms must be non-negative!");
+        let composite_device = create_composite_device(settings)?;
+        println!(
+            "The Raspberry Pi will be set up on connection with: \
+            setup_virtual_keyboard {} {}{}",
+            format_hex(composite_device.report_descriptor()),
+            composite_device.max_report_length(),
+            if composite_device.is_boot_keyboard() {
+                " boot"
+            } else {
+                ""
+            }
+        );
+        let transport = create_transport(
+            settings,
+            TransportContext {
+                connection_status_callback,
+                initial_frame: TaggedReport {
+                    report_id: DEVICE_CONFIG_REPORT_ID,
+                    report: &composite_device.device_config(),
+                }
+                .to_frame(),
+            },
+        )?;
+        Self::with_composite_device(settings, composite_device, transport)
+    }
+
+    /// Sends reports through `transport` instead of the one selected in the
+    /// settings. The device config is not sent.
+    #[cfg(test)]
+    pub fn with_transport(
+        settings: &VirtualDeviceSettings,
+        transport: Box<dyn Transport>,
+    ) -> Result<Self> {
+        Self::with_composite_device(
+            settings,
+            create_composite_device(settings)?,
+            transport,
+        )
+    }
+
+    fn with_composite_device(
+        settings: &VirtualDeviceSettings,
+        composite_device: CompositeDevice,
+        transport: Box<dyn Transport>,
+    ) -> Result<Self> {
+        if settings.min_key_down_ms < 0 || settings.min_report_interval_ms < 0 {
+            bail!(
+                "min_key_down_ms and min_report_interval_ms must be \
+                non-negative!"
+            );
         }
+        let keyboard_report_format =
+            settings.keyboard_report_format.enum_value_or_default();
+        let input_report: Box<dyn KeyboardReport> = match keyboard_report_format
+        {
+            KeyboardReportFormat::BOOT => Box::new(KeyboardInputReport::new()),
+            KeyboardReportFormat::NKRO => {
+                Box::new(NkroKeyboardInputReport::new())
+            }
+        };
         Ok(Self {
-            bluetooth_manager: BluetoothManager::new(
-                VirtualDeviceSelector::new(settings),
-                rfcomm_channel_id,
-                connection_status_callback,
+            transport,
+            composite_device,
+            input_report: RefCell::new(input_report),
+            key_owners: RefCell::new(KeyOwners::new()),
+            output_scheduler: RefCell::new(OutputScheduler::new(
+                Duration::from_millis(settings.min_key_down_ms as u64),
+                Duration::from_millis(settings.min_report_interval_ms as u64),
+            )),
+            key_rate_limiter: create_key_rate_limit(settings)?.map(
+                |token_bucket| {
+                    RefCell::new(KeyedTokenBuckets::new(token_bucket))
+                },
             ),
-            input_report: RefCell::new(KeyboardInputReport::new()),
-            last_sent_key_event: RefCell::new(None),
-            rate_limiting_threshold_ms: rate_limiting_threshold_ms as u128,
+            mouse_report: RefCell::new(MouseInputReport::new()),
+            mouse_button_owners: RefCell::new(KeyOwners::new()),
+            gamepad_report: RefCell::new(GamepadInputReport::new()),
+            gamepad_button_owners: RefCell::new(KeyOwners::new()),
+            consumer_control_report: RefCell::new(
+                ConsumerControlInputReport::new(),
+            ),
+            consumer_control_usage_owners: RefCell::new(KeyOwners::new()),
         })
     }
 
-    pub fn send_key_event(&self, key_event: KeyEvent) {
-        if !self.should_send_key_event(key_event) {
-            return;
+    /// Returns how many key presses were dropped by `key_rate_limit`.
+    pub fn num_suppressed_key_presses(&self) -> u64 {
+        self.key_rate_limiter
+            .as_ref()
+            .map_or(0, |key_rate_limiter| {
+                key_rate_limiter.borrow().num_suppressed()
+            })
+    }
+
+    /// Returns whether reports can be sent to the receiver.
+    pub fn is_connected(&self) -> bool {
+        self.transport.is_connected()
+    }
+
+    pub fn output_kinds(&self) -> &[OutputKind] {
+        self.composite_device.output_kinds()
+    }
+
+    pub fn send_output_event(&self, source: &KeySource, event: OutputEvent) {
+        match event {
+            OutputEvent::Key(key_event) => {
+                self.send_key_event(source, key_event)
+            }
+            OutputEvent::Mouse(mouse_event) => {
+                self.send_mouse_event(source, mouse_event)
+            }
+            OutputEvent::Gamepad(gamepad_event) => {
+                self.send_gamepad_event(source, gamepad_event)
+            }
+            OutputEvent::ConsumerControl(consumer_control_event) => {
+                self.send_consumer_control_event(source, consumer_control_event)
+            }
+        }
+    }
+
+    /// Sends the queued key changes that have become due, and the mouse motion
+    /// and gamepad axes changed since the last call. This should be called
+    /// periodically.
+    pub fn tick(&self, now: Instant) {
+        self.transport.connect(now);
+        self.send_due_input_reports(now);
+        if self.mouse_report.borrow().has_pending_motion() {
+            self.send_mouse_report();
+        }
+        if self.gamepad_report.borrow().has_unsent_changes() {
+            self.send_gamepad_report();
+        }
+    }
+
+    /// Releases all keys and buttons that are still pressed, and centers the
+    /// gamepad. This should be called when the remappers that pressed them are
+    /// discarded, otherwise those keys would keep occupying slots in the input
+    /// report.
+    pub fn release_all_keys(&self) {
+        let mut key_owners = self.key_owners.borrow_mut();
+        if !key_owners.is_empty() {
+            println!("Releasing keys held by their sources: {}", key_owners);
+            key_owners.clear();
+        }
+        self.output_scheduler.borrow_mut().clear();
+        if self.input_report.borrow_mut().release_all_keys() {
+            self.send_report(
+                OutputKind::Keyboard,
+                self.input_report.borrow().report(),
+            );
+        }
+        self.mouse_button_owners.borrow_mut().clear();
+        if self.mouse_report.borrow_mut().release_all_buttons() {
+            self.send_mouse_report();
+        }
+        self.gamepad_button_owners.borrow_mut().clear();
+        if self.gamepad_report.borrow_mut().reset() {
+            self.send_gamepad_report();
+        }
+        self.consumer_control_usage_owners.borrow_mut().clear();
+        if self
+            .consumer_control_report
+            .borrow_mut()
+            .release_all_usages()
+        {
+            self.send_consumer_control_report();
         }
-        self.last_sent_key_event.replace(Some(SentKeyEvent {
-            event: key_event,
-            timestamp: Instant::now(),
-        }));
+    }
+
+    fn send_key_event(&self, source: &KeySource, key_event: KeyEvent) {
+        let now = Instant::now();
+        let Some(key_event) = self.apply_key_rate_limit(key_event, now) else {
+            return;
+        };
         match key_event {
-            KeyEvent::Press(key_code) => self.send_key_press_event(key_code),
+            KeyEvent::Press(key_code) => {
+                self.queue_key_press_event(source, key_code)
+            }
             KeyEvent::Release(key_code) => {
-                self.send_key_release_event(key_code)
+                self.queue_key_release_event(source, key_code)
             }
             KeyEvent::PressAndRelease(key_code) => {
-                self.send_key_press_event(key_code);
-                self.send_key_release_event(key_code);
+                // Tapping a key that's being held by others would release it.
+                if !self.key_owners.borrow().is_held(key_code) {
+                    self.output_scheduler.borrow_mut().push_tap(key_code);
+                }
             }
             KeyEvent::ReleaseAndPress {
                 to_release,
                 to_press,
             } => {
-                self.send_key_release_event(to_release);
-                self.send_key_press_event(to_press);
+                self.queue_key_release_event(source, to_release);
+                self.queue_key_press_event(source, to_press);
             }
         }
+        self.send_due_input_reports(now);
     }
 
-    fn should_send_key_event(&self, new_event: KeyEvent) -> bool {
-        match self.last_sent_key_event.borrow().as_ref() {
-            Some(last_event) => {
-                if new_event != last_event.event {
-                    true
-                } else {
-                    Instant::now()
-                        .duration_since(last_event.timestamp)
-                        .as_millis()
-                        >= self.rate_limiting_threshold_ms
+    /// Motion is only sent in `tick()`, so that the motion along all axes is
+    /// combined into one report. Button changes are sent right away.
+    fn send_mouse_event(&self, source: &KeySource, mouse_event: MouseEvent) {
+        match mouse_event {
+            MouseEvent::Move { axis, distance } => {
+                self.mouse_report.borrow_mut().add_motion(axis, distance)
+            }
+            MouseEvent::Press(button) => {
+                if self.mouse_button_owners.borrow_mut().press(button, source) {
+                    self.update_mouse_button_state(
+                        button, /* is_pressed= */ true,
+                    );
+                }
+            }
+            MouseEvent::Release(button) => {
+                if self
+                    .mouse_button_owners
+                    .borrow_mut()
+                    .release(button, source)
+                {
+                    self.update_mouse_button_state(
+                        button, /* is_pressed= */ false,
+                    );
                 }
             }
-            None => true,
         }
     }
 
-    fn update_and_send_input_report(&self, key_code: c_char, is_pressed: bool) {
-        self.input_report
+    fn update_mouse_button_state(&self, button: u8, is_pressed: bool) {
+        self.mouse_report
             .borrow_mut()
-            .update_key_state(key_code, is_pressed);
-        self.bluetooth_manager
-            .send_data_to_target_device(self.input_report.borrow().report());
+            .update_button_state(button, is_pressed);
+        self.send_mouse_report();
+    }
+
+    /// Axes are only sent in `tick()`, same as mouse motion. Button and d-pad
+    /// changes are sent right away.
+    fn send_gamepad_event(
+        &self,
+        source: &KeySource,
+        gamepad_event: GamepadEvent,
+    ) {
+        let mut gamepad_report = self.gamepad_report.borrow_mut();
+        match gamepad_event {
+            GamepadEvent::MoveAxis { axis, value } => {
+                gamepad_report.update_axis_state(axis, value);
+                return;
+            }
+            GamepadEvent::Press(button) => {
+                if !self
+                    .gamepad_button_owners
+                    .borrow_mut()
+                    .press(button, source)
+                {
+                    return;
+                }
+                gamepad_report
+                    .update_button_state(button, /* is_pressed= */ true);
+            }
+            GamepadEvent::Release(button) => {
+                if !self
+                    .gamepad_button_owners
+                    .borrow_mut()
+                    .release(button, source)
+                {
+                    return;
+                }
+                gamepad_report
+                    .update_button_state(button, /* is_pressed= */ false);
+            }
+            GamepadEvent::MoveDpad(position) => {
+                gamepad_report.update_dpad_state(position);
+            }
+        }
+        drop(gamepad_report);
+        self.send_gamepad_report();
     }
 
-    #[inline]
-    fn send_key_press_event(&self, key_code: c_char) {
-        self.update_and_send_input_report(key_code, /* is_pressed= */ true)
+    /// Only key presses are limited, so that no key gets stuck.
+    fn apply_key_rate_limit(
+        &self,
+        key_event: KeyEvent,
+        now: Instant,
+    ) -> Option<KeyEvent> {
+        let (Some(key_rate_limiter), Some(key_code)) =
+            (self.key_rate_limiter.as_ref(), key_event.pressed_key_code())
+        else {
+            return Some(key_event);
+        };
+        if key_rate_limiter.borrow_mut().try_take(key_code, now) {
+            Some(key_event)
+        } else {
+            key_event.without_press()
+        }
+    }
+
+    fn send_due_input_reports(&self, now: Instant) {
+        // Without a minimum report interval, everything in the queue can be
+        // sent right away, one report after another.
+        while let Some(key_changes) =
+            self.output_scheduler.borrow_mut().pop_next_report(now)
+        {
+            let mut input_report = self.input_report.borrow_mut();
+            for key_change in key_changes {
+                input_report.update_key_state(
+                    key_change.key_code,
+                    key_change.is_pressed,
+                );
+            }
+            self.send_report(OutputKind::Keyboard, input_report.report());
+        }
+    }
+
+    fn send_mouse_report(&self) {
+        let report = self.mouse_report.borrow_mut().take_report();
+        self.send_report(OutputKind::Mouse, &report);
+    }
+
+    fn send_consumer_control_event(
+        &self,
+        source: &KeySource,
+        consumer_control_event: ConsumerControlEvent,
+    ) {
+        let (usage, is_pressed) = match consumer_control_event {
+            ConsumerControlEvent::Press(usage) => (usage, true),
+            ConsumerControlEvent::Release(usage) => (usage, false),
+        };
+        let mut usage_owners = self.consumer_control_usage_owners.borrow_mut();
+        let should_update = if is_pressed {
+            usage_owners.press(usage, source)
+        } else {
+            usage_owners.release(usage, source)
+        };
+        if should_update
+            && self
+                .consumer_control_report
+                .borrow_mut()
+                .update_usage_state(usage, is_pressed)
+        {
+            self.send_consumer_control_report();
+        }
+    }
+
+    fn send_consumer_control_report(&self) {
+        let report = self.consumer_control_report.borrow().report();
+        self.send_report(OutputKind::ConsumerControl, &report);
+    }
+
+    fn send_gamepad_report(&self) {
+        let report = self.gamepad_report.borrow_mut().take_report();
+        self.send_report(OutputKind::Gamepad, &report);
     }
 
-    #[inline]
-    fn send_key_release_event(&self, key_code: c_char) {
-        self.update_and_send_input_report(
-            key_code, /* is_pressed= */ false,
+    fn send_report(&self, output_kind: OutputKind, report: &[c_char]) {
+        // The frame would be dropped anyway.
+        if !self.is_connected() {
+            return;
+        }
+        if let Some(report) =
+            self.composite_device.tag_report(output_kind, report)
+        {
+            self.transport.send_frame(&report.to_frame());
+        }
+    }
+
+    fn queue_key_press_event(&self, source: &KeySource, key_code: c_char) {
+        if self.key_owners.borrow_mut().press(key_code, source) {
+            self.output_scheduler
+                .borrow_mut()
+                .push(key_code, /* is_pressed= */ true);
+        }
+    }
+
+    fn queue_key_release_event(&self, source: &KeySource, key_code: c_char) {
+        if self.key_owners.borrow_mut().release(key_code, source) {
+            self.output_scheduler
+                .borrow_mut()
+                .push(key_code, /* is_pressed= */ false);
+        }
+    }
+}
+
+fn create_composite_device(
+    settings: &VirtualDeviceSettings,
+) -> Result<CompositeDevice> {
+    let additional_output_kinds = settings
+        .additional_outputs
+        .iter()
+        .map(|output| match output.enum_value() {
+            Ok(AdditionalOutput::MOUSE) => Ok(OutputKind::Mouse),
+            Ok(AdditionalOutput::GAMEPAD) => Ok(OutputKind::Gamepad),
+            Ok(AdditionalOutput::CONSUMER_CONTROL) => {
+                Ok(OutputKind::ConsumerControl)
+            }
+            Err(value) => Err(anyhow!("Unknown output: {}", value)),
+        })
+        .collect::<Result<Vec<_>>>()?;
+    CompositeDevice::new(
+        settings.keyboard_report_format.enum_value_or_default(),
+        &additional_output_kinds,
+    )
+}
+
+/// Falls back to the deprecated `rate_limiting_threshold_ms`, so that older
+/// settings keep limiting each key the same way.
+fn create_key_rate_limit(
+    settings: &VirtualDeviceSettings,
+) -> Result<Option<TokenBucket>> {
+    let threshold_ms = settings.rate_limiting_threshold_ms;
+    if let Some(rate_limit) = settings.key_rate_limit.as_ref() {
+        if threshold_ms != 0 {
+            println!(
+                "Warning: rate_limiting_threshold_ms is deprecated, and is \
+                ignored since key_rate_limit is specified"
+            );
+        }
+        return Ok(Some(TokenBucket::try_from(rate_limit)?));
+    }
+    if threshold_ms == 0 {
+        return Ok(None);
+    }
+    println!(
+        "Warning: rate_limiting_threshold_ms is deprecated, please replace it \
+        with key_rate_limit {{ burst: 1 refill_interval_ms: {} }}",
+        threshold_ms
+    );
+    let mut rate_limit = RateLimit::new();
+    rate_limit.burst = 1;
+    rate_limit.refill_interval_ms = threshold_ms;
+    Ok(Some(TokenBucket::try_from(&rate_limit)?))
+}
+
+fn format_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use protobuf::text_format::parse_from_str as parse_proto_from_str;
+
+    use super::memory_transport::MemoryTransport;
+    use super::*;
+
+    fn create_device(
+        settings: &VirtualDeviceSettings,
+    ) -> (VirtualDevice, MemoryTransport) {
+        let transport = MemoryTransport::default();
+        let device = VirtualDevice::with_transport(
+            settings,
+            Box::new(transport.clone()),
         )
+        .unwrap();
+        (device, transport)
+    }
+
+    #[test]
+    fn key_events_are_framed_without_report_id() {
+        let (device, transport) = create_device(&Default::default());
+        let source = KeySource::new("test".to_string());
+
+        device.send_output_event(&source, KeyEvent::Press(0x04).into());
+        device.send_output_event(&source, KeyEvent::Release(0x04).into());
+
+        assert_eq!(
+            transport.take_frames(),
+            vec![
+                vec![0, 7, 0, 0x04, 0, 0, 0, 0, 0],
+                vec![0, 7, 0, 0, 0, 0, 0, 0, 0],
+            ]
+        );
+    }
+
+    #[test]
+    fn key_events_are_framed_with_report_id() {
+        let mut settings = VirtualDeviceSettings::new();
+        settings
+            .additional_outputs
+            .push(AdditionalOutput::MOUSE.into());
+        let (device, transport) = create_device(&settings);
+        let source = KeySource::new("test".to_string());
+
+        device.send_output_event(&source, KeyEvent::Press(0x05).into());
+
+        let report_id = OutputKind::Keyboard as c_char;
+        assert_eq!(
+            transport.take_frames(),
+            vec![vec![report_id, 7, 0, 0x05, 0, 0, 0, 0, 0]]
+        );
+    }
+
+    #[test]
+    fn deprecated_threshold_limits_each_key() {
+        let settings = parse_proto_from_str::<VirtualDeviceSettings>(
+            "rate_limiting_threshold_ms: 1000",
+        )
+        .unwrap();
+        let (device, transport) = create_device(&settings);
+        let source = KeySource::new("test".to_string());
+
+        device.send_output_event(&source, KeyEvent::Press(0x04).into());
+        device.send_output_event(&source, KeyEvent::Release(0x04).into());
+        device.send_output_event(&source, KeyEvent::Press(0x04).into());
+        device.send_output_event(&source, KeyEvent::Press(0x05).into());
+
+        assert_eq!(device.num_suppressed_key_presses(), 1);
+        assert_eq!(transport.take_frames().len(), 3);
     }
 }
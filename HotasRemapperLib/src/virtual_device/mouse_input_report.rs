@@ -0,0 +1,117 @@
+use std::ffi::c_char;
+
+use super::MouseAxis;
+
+enum ReportField {
+    ButtonStates = 0,
+    X = 1,
+    Y = 2,
+    Wheel = 3,
+    EndExclusive = 4,
+}
+
+pub(crate) const REPORT_LENGTH: usize = ReportField::EndExclusive as usize;
+
+// Usages of the buttons on the button page start from 1, so button N is
+// reported as bit N - 1.
+const NUM_BUTTONS: u8 = 5;
+
+#[rustfmt::skip]
+pub(crate) const REPORT_DESCRIPTOR: &[u8] = &[
+    0x05, 0x01, // Usage Page (Generic Desktop)
+    0x09, 0x02, // Usage (Mouse)
+    0xA1, 0x01, // Collection (Application)
+    0x09, 0x01, //   Usage (Pointer)
+    0xA1, 0x00, //   Collection (Physical)
+    0x05, 0x09, //     Usage Page (Button)
+    0x19, 0x01, //     Usage Minimum (1)
+    0x29, 0x05, //     Usage Maximum (5)
+    0x15, 0x00, //     Logical Minimum (0)
+    0x25, 0x01, //     Logical Maximum (1)
+    0x95, 0x05, //     Report Count (5)
+    0x75, 0x01, //     Report Size (1)
+    0x81, 0x02, //     Input (Data, Variable, Absolute)
+    0x95, 0x01, //     Report Count (1)
+    0x75, 0x03, //     Report Size (3)
+    0x81, 0x01, //     Input (Constant)
+    0x05, 0x01, //     Usage Page (Generic Desktop)
+    0x09, 0x30, //     Usage (X)
+    0x09, 0x31, //     Usage (Y)
+    0x09, 0x38, //     Usage (Wheel)
+    0x15, 0x81, //     Logical Minimum (-127)
+    0x25, 0x7F, //     Logical Maximum (127)
+    0x75, 0x08, //     Report Size (8)
+    0x95, 0x03, //     Report Count (3)
+    0x81, 0x06, //     Input (Data, Variable, Relative)
+    0xC0,       //   End Collection
+    0xC0,       // End Collection
+];
+
+/// The boot protocol mouse report with a wheel, i.e. one byte for button states
+/// and one signed byte for the relative motion along each axis. Its layout must
+/// match the report descriptor set up on the Raspberry Pi.
+/// Motion is accumulated until the next report is taken. Motion that doesn't
+/// fit in one report is carried over to the following ones.
+pub(crate) struct MouseInputReport {
+    button_states: u8,
+    // Indexed by `MouseAxis`.
+    pending_motion: [i32; 3],
+}
+
+impl MouseInputReport {
+    pub fn new() -> Self {
+        Self {
+            button_states: 0,
+            pending_motion: [0; 3],
+        }
+    }
+
+    pub fn update_button_state(&mut self, button: u8, is_pressed: bool) {
+        if button >= NUM_BUTTONS {
+            println!("Mouse button out of range (button: {})", button);
+            return;
+        }
+        if is_pressed {
+            self.button_states |= 1 << button;
+        } else {
+            self.button_states &= !(1 << button);
+        }
+    }
+
+    pub fn add_motion(&mut self, axis: MouseAxis, distance: i32) {
+        let pending_motion = &mut self.pending_motion[axis as usize];
+        *pending_motion = pending_motion.saturating_add(distance);
+    }
+
+    pub fn has_pending_motion(&self) -> bool {
+        self.pending_motion.iter().any(|&motion| motion != 0)
+    }
+
+    /// Returns whether any button was pressed.
+    pub fn release_all_buttons(&mut self) -> bool {
+        let has_pressed_buttons = self.button_states != 0;
+        self.button_states = 0;
+        self.pending_motion.fill(0);
+        has_pressed_buttons
+    }
+
+    /// Returns the report with as much of the pending motion as fits in it.
+    pub fn take_report(&mut self) -> [c_char; REPORT_LENGTH] {
+        let mut report = [0; REPORT_LENGTH];
+        report[ReportField::ButtonStates as usize] =
+            self.button_states as c_char;
+        for (field, axis) in [
+            (ReportField::X, MouseAxis::X),
+            (ReportField::Y, MouseAxis::Y),
+            (ReportField::Wheel, MouseAxis::Wheel),
+        ] {
+            let pending_motion = &mut self.pending_motion[axis as usize];
+            let motion = (*pending_motion)
+                .clamp(i8::MIN as i32 + 1, i8::MAX as i32)
+                as i8;
+            *pending_motion -= motion as i32;
+            report[field as usize] = motion as c_char;
+        }
+        report
+    }
+}
@@ -0,0 +1,378 @@
+use std::cell::Cell;
+use std::cell::RefCell;
+use std::ffi::c_char;
+use std::io::ErrorKind;
+use std::io::Read;
+use std::io::Result as IoResult;
+use std::io::Write;
+use std::net::SocketAddr;
+use std::net::TcpStream;
+use std::net::ToSocketAddrs;
+use std::net::UdpSocket;
+use std::sync::mpsc;
+use std::sync::mpsc::Receiver;
+use std::sync::mpsc::TryRecvError;
+use std::thread;
+use std::time::Duration;
+use std::time::Instant;
+
+use anyhow::bail;
+use anyhow::Result;
+
+use super::transport::Transport;
+use super::transport::TransportContext;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(3);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum NetworkProtocol {
+    Tcp,
+    Udp,
+}
+
+enum Socket {
+    Tcp(TcpStream),
+    Udp(UdpSocket),
+}
+
+impl Socket {
+    /// Resolves `address` and connects to it. Blocks, so it's only called
+    /// from a background thread.
+    fn connect(address: &str, protocol: NetworkProtocol) -> IoResult<Self> {
+        let mut last_error = None;
+        for socket_address in address.to_socket_addrs()? {
+            match Self::connect_to(socket_address, protocol) {
+                Ok(socket) => return Ok(socket),
+                Err(e) => last_error = Some(e),
+            }
+        }
+        Err(last_error.unwrap_or_else(|| ErrorKind::AddrNotAvailable.into()))
+    }
+
+    fn connect_to(
+        socket_address: SocketAddr,
+        protocol: NetworkProtocol,
+    ) -> IoResult<Self> {
+        match protocol {
+            NetworkProtocol::Tcp => {
+                let stream = TcpStream::connect_timeout(
+                    &socket_address,
+                    CONNECT_TIMEOUT,
+                )?;
+                stream.set_nodelay(true)?;
+                stream.set_nonblocking(true)?;
+                Ok(Self::Tcp(stream))
+            }
+            NetworkProtocol::Udp => {
+                let local_address: SocketAddr = if socket_address.is_ipv4() {
+                    ([0, 0, 0, 0], 0).into()
+                } else {
+                    ([0u16; 8], 0).into()
+                };
+                let socket = UdpSocket::bind(local_address)?;
+                socket.connect(socket_address)?;
+                socket.set_nonblocking(true)?;
+                Ok(Self::Udp(socket))
+            }
+        }
+    }
+
+    /// Sends the whole frame. A frame that's only partially written would
+    /// corrupt the stream, so `WouldBlock` is treated as an error.
+    fn send(&mut self, frame: &[u8]) -> IoResult<()> {
+        match self {
+            Self::Tcp(stream) => stream.write_all(frame),
+            Self::Udp(socket) => socket.send(frame).map(|_| ()),
+        }
+    }
+
+    /// Returns an error if the connection is found to be closed. The receiver
+    /// never sends anything, so whatever is read is discarded.
+    fn poll(&mut self) -> IoResult<()> {
+        let mut buffer = [0u8; 64];
+        loop {
+            let result = match self {
+                Self::Tcp(stream) => stream.read(&mut buffer),
+                Self::Udp(socket) => socket.recv(&mut buffer),
+            };
+            match result {
+                Ok(0) if matches!(self, Self::Tcp(_)) => {
+                    return Err(ErrorKind::UnexpectedEof.into())
+                }
+                Ok(_) => continue,
+                Err(e) if e.kind() == ErrorKind::WouldBlock => return Ok(()),
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+enum ConnectionState {
+    Disconnected,
+    /// Waiting for the background thread to connect.
+    Connecting(Receiver<IoResult<Socket>>),
+    Connected(Socket),
+}
+
+/// Sends frames to the receiver at `address` over TCP or UDP, and reconnects
+/// every `reconnect_interval` while disconnected.
+///
+/// UDP has no connection to lose, so it's considered connected unless the
+/// receiver's host reports that nothing is listening on the port. The initial
+/// frame is resent every `reconnect_interval` instead, in case it was lost or
+/// the receiver was restarted.
+pub(crate) struct NetworkTransport {
+    address: String,
+    protocol: NetworkProtocol,
+    reconnect_interval: Duration,
+    context: TransportContext,
+    state: RefCell<ConnectionState>,
+    /// When the last connection attempt was started, or for UDP, when the
+    /// initial frame was last sent.
+    last_attempt_time: Cell<Option<Instant>>,
+}
+
+impl NetworkTransport {
+    pub fn new(
+        address: &str,
+        protocol: NetworkProtocol,
+        reconnect_interval: Duration,
+        context: TransportContext,
+    ) -> Result<Self> {
+        if address.is_empty() {
+            bail!("network_address must be specified for {:?}", protocol);
+        }
+        Ok(Self {
+            address: address.to_string(),
+            protocol,
+            reconnect_interval,
+            context,
+            state: RefCell::new(ConnectionState::Disconnected),
+            last_attempt_time: Cell::new(None),
+        })
+    }
+
+    fn is_attempt_due(&self, now: Instant) -> bool {
+        match self.last_attempt_time.get() {
+            Some(last_attempt_time) => {
+                now.saturating_duration_since(last_attempt_time)
+                    >= self.reconnect_interval
+            }
+            None => true,
+        }
+    }
+
+    fn start_connecting(&self) -> ConnectionState {
+        let (sender, receiver) = mpsc::channel();
+        let address = self.address.clone();
+        let protocol = self.protocol;
+        thread::spawn(move || {
+            // The transport may have been dropped in the meantime, in which
+            // case the socket is simply closed.
+            let _ = sender.send(Socket::connect(&address, protocol));
+        });
+        ConnectionState::Connecting(receiver)
+    }
+
+    /// Sends the initial frame, and only reports being connected if that
+    /// succeeds.
+    fn handle_connected(&self, mut socket: Socket) -> ConnectionState {
+        if let Err(e) = socket.send(&to_bytes(&self.context.initial_frame)) {
+            println!(
+                "Failed to send the initial frame to {}: {}",
+                self.address, e
+            );
+            return ConnectionState::Disconnected;
+        }
+        println!("Connected to {} over {:?}", self.address, self.protocol);
+        self.context
+            .report_connection_status(/* is_connected= */ true);
+        ConnectionState::Connected(socket)
+    }
+
+    fn handle_disconnected(&self, error: std::io::Error) -> ConnectionState {
+        println!("Disconnected from {}: {}", self.address, error);
+        self.context
+            .report_connection_status(/* is_connected= */ false);
+        ConnectionState::Disconnected
+    }
+}
+
+impl Transport for NetworkTransport {
+    fn send_frame(&self, frame: &[c_char]) {
+        let mut state = self.state.borrow_mut();
+        let ConnectionState::Connected(socket) = &mut *state else {
+            return;
+        };
+        if let Err(e) = socket.send(&to_bytes(frame)) {
+            *state = self.handle_disconnected(e);
+        }
+    }
+
+    fn connect(&self, now: Instant) {
+        let mut state = self.state.borrow_mut();
+        let new_state = match &mut *state {
+            ConnectionState::Disconnected => {
+                if !self.is_attempt_due(now) {
+                    return;
+                }
+                self.last_attempt_time.set(Some(now));
+                self.start_connecting()
+            }
+            ConnectionState::Connecting(receiver) => {
+                match receiver.try_recv() {
+                    Ok(Ok(socket)) => self.handle_connected(socket),
+                    Ok(Err(e)) => {
+                        println!(
+                            "Failed to connect to {}: {}",
+                            self.address, e
+                        );
+                        ConnectionState::Disconnected
+                    }
+                    Err(TryRecvError::Empty) => return,
+                    Err(TryRecvError::Disconnected) => {
+                        ConnectionState::Disconnected
+                    }
+                }
+            }
+            ConnectionState::Connected(socket) => {
+                if let Err(e) = socket.poll() {
+                    self.handle_disconnected(e)
+                } else if self.protocol == NetworkProtocol::Udp
+                    && self.is_attempt_due(now)
+                {
+                    self.last_attempt_time.set(Some(now));
+                    match socket.send(&to_bytes(&self.context.initial_frame)) {
+                        Ok(()) => return,
+                        Err(e) => self.handle_disconnected(e),
+                    }
+                } else {
+                    return;
+                }
+            }
+        };
+        *state = new_state;
+    }
+
+    fn is_connected(&self) -> bool {
+        matches!(*self.state.borrow(), ConnectionState::Connected(_))
+    }
+}
+
+fn to_bytes(frame: &[c_char]) -> Vec<u8> {
+    frame.iter().map(|&byte| byte as u8).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+
+    use super::*;
+    use crate::virtual_device::transport::testing::connect_until_status;
+    use crate::virtual_device::transport::testing::create_context;
+    use crate::virtual_device::transport::testing::INITIAL_FRAME;
+
+    const RECONNECT_INTERVAL: Duration = Duration::from_millis(10);
+    const REPORT_FRAME: [c_char; 5] = [1, 3, 4, 5, 6];
+
+    fn create_transport(
+        address: SocketAddr,
+        protocol: NetworkProtocol,
+    ) -> NetworkTransport {
+        NetworkTransport::new(
+            &address.to_string(),
+            protocol,
+            RECONNECT_INTERVAL,
+            create_context(),
+        )
+        .unwrap()
+    }
+
+    fn read_frame(stream: &mut TcpStream, length: usize) -> Vec<c_char> {
+        let mut buffer = vec![0u8; length];
+        stream.read_exact(&mut buffer).unwrap();
+        buffer.into_iter().map(|byte| byte as c_char).collect()
+    }
+
+    fn receive_frame(socket: &UdpSocket) -> Vec<c_char> {
+        let mut buffer = [0u8; 64];
+        let length = socket.recv(&mut buffer).unwrap();
+        buffer[..length]
+            .iter()
+            .map(|&byte| byte as c_char)
+            .collect()
+    }
+
+    #[test]
+    fn tcp_sends_initial_frame_then_reports() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let transport = create_transport(
+            listener.local_addr().unwrap(),
+            NetworkProtocol::Tcp,
+        );
+
+        connect_until_status(&transport, /* is_connected= */ true);
+        let (mut stream, _) = listener.accept().unwrap();
+        transport.send_frame(&REPORT_FRAME);
+
+        assert_eq!(read_frame(&mut stream, INITIAL_FRAME.len()), INITIAL_FRAME);
+        assert_eq!(read_frame(&mut stream, REPORT_FRAME.len()), REPORT_FRAME);
+    }
+
+    #[test]
+    fn tcp_reconnects_after_connection_is_dropped() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let transport = create_transport(
+            listener.local_addr().unwrap(),
+            NetworkProtocol::Tcp,
+        );
+        connect_until_status(&transport, /* is_connected= */ true);
+        drop(listener.accept().unwrap());
+
+        connect_until_status(&transport, /* is_connected= */ false);
+        connect_until_status(&transport, /* is_connected= */ true);
+        let (mut stream, _) = listener.accept().unwrap();
+        transport.send_frame(&REPORT_FRAME);
+
+        assert_eq!(read_frame(&mut stream, INITIAL_FRAME.len()), INITIAL_FRAME);
+        assert_eq!(read_frame(&mut stream, REPORT_FRAME.len()), REPORT_FRAME);
+    }
+
+    #[test]
+    fn udp_sends_one_frame_per_datagram() {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        socket
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let transport = create_transport(
+            socket.local_addr().unwrap(),
+            NetworkProtocol::Udp,
+        );
+
+        connect_until_status(&transport, /* is_connected= */ true);
+        transport.send_frame(&REPORT_FRAME);
+
+        assert_eq!(receive_frame(&socket), INITIAL_FRAME);
+        assert_eq!(receive_frame(&socket), REPORT_FRAME);
+    }
+
+    #[test]
+    fn udp_resends_initial_frame() {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        socket
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let transport = create_transport(
+            socket.local_addr().unwrap(),
+            NetworkProtocol::Udp,
+        );
+        connect_until_status(&transport, /* is_connected= */ true);
+        assert_eq!(receive_frame(&socket), INITIAL_FRAME);
+
+        thread::sleep(RECONNECT_INTERVAL);
+        transport.connect(Instant::now());
+
+        assert_eq!(receive_frame(&socket), INITIAL_FRAME);
+    }
+}
@@ -0,0 +1,88 @@
+use std::ffi::c_char;
+
+use super::keyboard_input_report::update_modifier_key_state;
+use super::keyboard_input_report::KeyboardReport;
+
+enum ReportField {
+    ModifierKeyStates = 0,
+    KeyStatesBegin = 1,
+    KeyStatesEndExclusive = 29,
+}
+
+pub(crate) const REPORT_LENGTH: usize =
+    ReportField::KeyStatesEndExclusive as usize;
+
+// Each non-modifier key (usages 0x00 to 0xDF) has one bit in the report.
+const NUM_KEYS: usize = (ReportField::KeyStatesEndExclusive as usize
+    - ReportField::KeyStatesBegin as usize)
+    * 8;
+
+#[rustfmt::skip]
+pub(crate) const REPORT_DESCRIPTOR: &[u8] = &[
+    0x05, 0x01, // Usage Page (Generic Desktop)
+    0x09, 0x06, // Usage (Keyboard)
+    0xA1, 0x01, // Collection (Application)
+    0x05, 0x07, //   Usage Page (Keyboard)
+    0x19, 0xE0, //   Usage Minimum (Left Control)
+    0x29, 0xE7, //   Usage Maximum (Right GUI)
+    0x15, 0x00, //   Logical Minimum (0)
+    0x25, 0x01, //   Logical Maximum (1)
+    0x75, 0x01, //   Report Size (1)
+    0x95, 0x08, //   Report Count (8)
+    0x81, 0x02, //   Input (Data, Variable, Absolute)
+    0x19, 0x00, //   Usage Minimum (0)
+    0x29, 0xDF, //   Usage Maximum (0xDF)
+    0x95, 0xE0, //   Report Count (224)
+    0x81, 0x02, //   Input (Data, Variable, Absolute)
+    0xC0,       // End Collection
+];
+
+/// The N-key rollover report, with one byte for modifier keys and a bitmap for
+/// other keys, so that any number of keys can be held at once. This is not
+/// supported by the boot protocol.
+pub(crate) struct NkroKeyboardInputReport {
+    report: [c_char; REPORT_LENGTH],
+}
+
+impl NkroKeyboardInputReport {
+    pub fn new() -> Self {
+        Self {
+            report: [0; REPORT_LENGTH],
+        }
+    }
+}
+
+impl KeyboardReport for NkroKeyboardInputReport {
+    fn update_key_state(&mut self, key_code: c_char, is_pressed: bool) {
+        if update_modifier_key_state(
+            &mut self.report[ReportField::ModifierKeyStates as usize],
+            key_code,
+            is_pressed,
+        ) {
+            return;
+        }
+        let key_index = key_code as u8 as usize;
+        if key_index >= NUM_KEYS {
+            println!("Key code out of range (keycode: {})", key_code);
+            return;
+        }
+        let key_states = &mut self.report
+            [ReportField::KeyStatesBegin as usize + key_index / 8];
+        let key_bit = 1 << (key_index % 8);
+        if is_pressed {
+            *key_states |= key_bit;
+        } else {
+            *key_states &= !key_bit;
+        }
+    }
+
+    fn release_all_keys(&mut self) -> bool {
+        let has_pressed_keys = self.report.iter().any(|&state| state != 0);
+        self.report.fill(0x00);
+        has_pressed_keys
+    }
+
+    fn report(&self) -> &[c_char] {
+        &self.report
+    }
+}
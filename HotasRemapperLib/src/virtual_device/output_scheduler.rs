@@ -0,0 +1,154 @@
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::collections::VecDeque;
+use std::ffi::c_char;
+use std::time::Duration;
+use std::time::Instant;
+
+/// Taps beyond this many pending changes are dropped, so that an input that
+/// keeps tapping, e.g. an axis that's being moved, can't delay the reports
+/// indefinitely.
+const MAX_PENDING_KEY_CHANGES: usize = 16;
+
+#[derive(Clone, Copy)]
+pub(crate) struct KeyChange {
+    pub key_code: c_char,
+    pub is_pressed: bool,
+}
+
+/// Queues key changes and decides when they can be sent, so that every change
+/// stays in the input report long enough for the console to see it. Changes
+/// are sent in order, and changes to different keys are coalesced into one
+/// report whenever possible.
+pub(crate) struct OutputScheduler {
+    min_key_down: Duration,
+    min_report_interval: Duration,
+    pending_key_changes: VecDeque<KeyChange>,
+    key_press_times: HashMap<c_char, Instant>,
+    last_report_time: Option<Instant>,
+}
+
+impl OutputScheduler {
+    pub fn new(min_key_down: Duration, min_report_interval: Duration) -> Self {
+        Self {
+            min_key_down,
+            min_report_interval,
+            pending_key_changes: Default::default(),
+            key_press_times: Default::default(),
+            last_report_time: None,
+        }
+    }
+
+    pub fn push(&mut self, key_code: c_char, is_pressed: bool) {
+        self.pending_key_changes.push_back(KeyChange {
+            key_code,
+            is_pressed,
+        });
+    }
+
+    /// Queues a press and a release of `key_code`. The tap is dropped if the
+    /// key is already going to be pressed, or if too many changes are pending,
+    /// which only makes the key repeat fewer times.
+    pub fn push_tap(&mut self, key_code: c_char) {
+        if self.pending_key_changes.len() + 2 > MAX_PENDING_KEY_CHANGES
+            || self.pending_key_changes.iter().any(|key_change| {
+                key_change.key_code == key_code && key_change.is_pressed
+            })
+        {
+            return;
+        }
+        self.push(key_code, /* is_pressed= */ true);
+        self.push(key_code, /* is_pressed= */ false);
+    }
+
+    /// Returns the changes to apply to the next report, or None if no report
+    /// should be sent at `now`.
+    pub fn pop_next_report(&mut self, now: Instant) -> Option<Vec<KeyChange>> {
+        if let Some(last_report_time) = self.last_report_time {
+            if now.saturating_duration_since(last_report_time)
+                < self.min_report_interval
+            {
+                return None;
+            }
+        }
+        let mut key_changes = vec![];
+        let mut changed_keys = HashSet::new();
+        while let Some(&key_change) = self.pending_key_changes.front() {
+            // A key can only change once per report, otherwise the changes
+            // would cancel each other out.
+            if changed_keys.contains(&key_change.key_code) {
+                break;
+            }
+            if !key_change.is_pressed {
+                if let Some(&press_time) =
+                    self.key_press_times.get(&key_change.key_code)
+                {
+                    if now.saturating_duration_since(press_time)
+                        < self.min_key_down
+                    {
+                        break;
+                    }
+                }
+            }
+            self.pending_key_changes.pop_front();
+            changed_keys.insert(key_change.key_code);
+            if key_change.is_pressed {
+                self.key_press_times.insert(key_change.key_code, now);
+            } else {
+                self.key_press_times.remove(&key_change.key_code);
+            }
+            key_changes.push(key_change);
+        }
+        if key_changes.is_empty() {
+            return None;
+        }
+        self.last_report_time = Some(now);
+        Some(key_changes)
+    }
+
+    /// Drops all pending changes, e.g. when all keys are released at once.
+    pub fn clear(&mut self) {
+        self.pending_key_changes.clear();
+        self.key_press_times.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn count_pending_presses(scheduler: &OutputScheduler) -> usize {
+        scheduler
+            .pending_key_changes
+            .iter()
+            .filter(|key_change| key_change.is_pressed)
+            .count()
+    }
+
+    #[test]
+    fn drops_tap_of_key_already_pending() {
+        let mut scheduler =
+            OutputScheduler::new(Duration::ZERO, Duration::ZERO);
+
+        scheduler.push_tap(0x04);
+        scheduler.push_tap(0x04);
+        scheduler.push_tap(0x05);
+
+        assert_eq!(count_pending_presses(&scheduler), 2);
+    }
+
+    #[test]
+    fn caps_pending_taps() {
+        let mut scheduler =
+            OutputScheduler::new(Duration::ZERO, Duration::ZERO);
+
+        for key_code in 0..MAX_PENDING_KEY_CHANGES as c_char {
+            scheduler.push_tap(key_code);
+        }
+
+        assert_eq!(
+            scheduler.pending_key_changes.len(),
+            MAX_PENDING_KEY_CHANGES
+        );
+    }
+}
@@ -0,0 +1,62 @@
+// Tags of the short items that we care about, i.e. the prefix byte without the
+// lowest 2 bits, which tell the size of the item data.
+const INPUT_TAG: u8 = 0x80;
+const COLLECTION_TAG: u8 = 0xA0;
+const REPORT_SIZE_TAG: u8 = 0x74;
+const REPORT_COUNT_TAG: u8 = 0x94;
+
+struct Item {
+    tag: u8,
+    data: u32,
+    // Index of the byte right after the item.
+    end: usize,
+}
+
+/// Iterates over the short items of a HID report descriptor. Long items are not
+/// used by any of our descriptors.
+fn parse_items(descriptor: &[u8]) -> impl Iterator<Item = Item> + '_ {
+    let mut index = 0;
+    std::iter::from_fn(move || {
+        let &prefix = descriptor.get(index)?;
+        let data_size = match prefix & 0x03 {
+            3 => 4,
+            size => size as usize,
+        };
+        let data_begin = index + 1;
+        index = (data_begin + data_size).min(descriptor.len());
+        let data = descriptor[data_begin..index]
+            .iter()
+            .rev()
+            .fold(0, |data, &byte| (data << 8) | byte as u32);
+        Some(Item {
+            tag: prefix & 0xFC,
+            data,
+            end: index,
+        })
+    })
+}
+
+/// Returns the index right after the first Collection item, or `None` if there
+/// is no collection.
+pub(super) fn find_collection_end(descriptor: &[u8]) -> Option<usize> {
+    parse_items(descriptor)
+        .find(|item| item.tag == COLLECTION_TAG)
+        .map(|item| item.end)
+}
+
+/// Returns the length in bytes of the input report described by `descriptor`,
+/// which must only describe one report, excluding the report ID.
+pub(super) fn get_input_report_length(descriptor: &[u8]) -> usize {
+    let mut report_size = 0;
+    let mut report_count = 0;
+    let mut num_bits = 0;
+    for item in parse_items(descriptor) {
+        match item.tag {
+            REPORT_SIZE_TAG => report_size = item.data as usize,
+            REPORT_COUNT_TAG => report_count = item.data as usize,
+            INPUT_TAG => num_bits += report_size * report_count,
+            _ => (),
+        }
+    }
+    num_bits.div_ceil(8)
+}
@@ -0,0 +1,69 @@
+use std::ffi::c_char;
+use std::pin::Pin;
+
+use anyhow::anyhow;
+use anyhow::Result;
+
+use super::bluetooth_device::DeviceInfo;
+use super::bluetooth_manager::SelectDevice;
+use super::transport::Transport;
+use super::transport::TransportContext;
+use crate::settings::VirtualDeviceSettings;
+
+type BluetoothManager =
+    super::bluetooth_manager::BluetoothManager<VirtualDeviceSelector>;
+
+struct VirtualDeviceSelector {
+    mac_address: String,
+}
+
+impl VirtualDeviceSelector {
+    pub fn new(settings: &VirtualDeviceSettings) -> Self {
+        Self {
+            mac_address: settings.mac_address.clone(),
+        }
+    }
+}
+
+impl SelectDevice for VirtualDeviceSelector {
+    fn is_target_device(&self, device_info: &DeviceInfo) -> bool {
+        device_info.mac_address == self.mac_address
+    }
+}
+
+/// Sends frames over a Bluetooth RFCOMM channel to the device with
+/// `mac_address`, which connects to us on its own. It's considered connected
+/// while the channel is opened.
+pub(crate) struct RfcommTransport {
+    bluetooth_manager: Pin<Box<BluetoothManager>>,
+}
+
+impl RfcommTransport {
+    pub fn new(
+        settings: &VirtualDeviceSettings,
+        context: TransportContext,
+    ) -> Result<Self> {
+        let rfcomm_channel_id = u8::try_from(settings.rfcomm_channel_id)
+            .map_err(|e| {
+                anyhow!("Cannot convert rfcomm_channel_id to u8: {}", e)
+            })?;
+        Ok(Self {
+            bluetooth_manager: BluetoothManager::new(
+                VirtualDeviceSelector::new(settings),
+                rfcomm_channel_id,
+                context.connection_status_callback,
+                context.initial_frame,
+            ),
+        })
+    }
+}
+
+impl Transport for RfcommTransport {
+    fn send_frame(&self, frame: &[c_char]) {
+        self.bluetooth_manager.send_data_to_target_device(frame);
+    }
+
+    fn is_connected(&self) -> bool {
+        self.bluetooth_manager.is_target_device_connected()
+    }
+}
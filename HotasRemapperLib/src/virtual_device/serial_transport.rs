@@ -0,0 +1,364 @@
+use std::cell::Cell;
+use std::cell::RefCell;
+use std::ffi::c_char;
+use std::fs::File;
+use std::fs::OpenOptions;
+use std::io::Error as IoError;
+use std::io::ErrorKind;
+use std::io::Read;
+use std::io::Result as IoResult;
+use std::io::Write;
+use std::mem::MaybeUninit;
+use std::os::fd::AsRawFd;
+use std::os::unix::fs::OpenOptionsExt;
+use std::time::Duration;
+use std::time::Instant;
+
+use anyhow::bail;
+use anyhow::Result;
+
+use super::transport::Transport;
+use super::transport::TransportContext;
+
+const DEFAULT_BAUD_RATE: i32 = 115200;
+/// If more than this many bytes are waiting to be written, the receiver is
+/// considered to have stopped reading.
+const MAX_UNSENT_BYTES: usize = 4096;
+
+/// The port is non-blocking, so that a slow receiver can't stall us. Whatever
+/// can't be written right away is kept and written later, so that frames are
+/// never cut short.
+struct OpenedPort {
+    file: File,
+    unsent_bytes: Vec<u8>,
+}
+
+impl OpenedPort {
+    fn write_frame(&mut self, frame: &[c_char]) -> IoResult<()> {
+        if self.unsent_bytes.len() + frame.len() > MAX_UNSENT_BYTES {
+            return Err(IoError::new(
+                ErrorKind::WouldBlock,
+                "the receiver stopped reading",
+            ));
+        }
+        self.unsent_bytes
+            .extend(frame.iter().map(|&byte| byte as u8));
+        self.flush()
+    }
+
+    /// Writes as many unsent bytes as the port takes.
+    fn flush(&mut self) -> IoResult<()> {
+        while !self.unsent_bytes.is_empty() {
+            match self.file.write(&self.unsent_bytes) {
+                Ok(0) => return Err(ErrorKind::WriteZero.into()),
+                Ok(length) => {
+                    self.unsent_bytes.drain(..length);
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => return Ok(()),
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns an error if the port is found to be closed, e.g. by a hangup.
+    /// The receiver never sends anything, so whatever is read is discarded.
+    fn poll(&mut self) -> IoResult<()> {
+        let mut buffer = [0u8; 64];
+        loop {
+            match self.file.read(&mut buffer) {
+                Ok(0) => return Err(ErrorKind::UnexpectedEof.into()),
+                Ok(_) => continue,
+                Err(e) if e.kind() == ErrorKind::WouldBlock => return Ok(()),
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+/// Sends frames to a microcontroller over a serial port, e.g. a USB CDC-ACM
+/// device or a UART adapter. The port is opened in raw mode, so frames are
+/// streamed as is, the same as over RFCOMM.
+///
+/// The port is considered connected while it's open, and is reopened every
+/// `reconnect_interval` after it fails, e.g. when the device is unplugged.
+pub(crate) struct SerialTransport {
+    path: String,
+    baud_rate: libc::speed_t,
+    reconnect_interval: Duration,
+    context: TransportContext,
+    port: RefCell<Option<OpenedPort>>,
+    last_attempt_time: Cell<Option<Instant>>,
+}
+
+impl SerialTransport {
+    pub fn new(
+        path: &str,
+        baud_rate: i32,
+        reconnect_interval: Duration,
+        context: TransportContext,
+    ) -> Result<Self> {
+        if path.is_empty() {
+            bail!("serial_port_path must be specified");
+        }
+        Ok(Self {
+            path: path.to_string(),
+            baud_rate: get_speed(baud_rate)?,
+            reconnect_interval,
+            context,
+            port: RefCell::new(None),
+            last_attempt_time: Cell::new(None),
+        })
+    }
+
+    fn open_port(&self) -> IoResult<File> {
+        // Non-blocking, so that opening doesn't wait for the carrier detect
+        // line either.
+        let port = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .custom_flags(libc::O_NOCTTY | libc::O_NONBLOCK)
+            .open(&self.path)?;
+        let fd = port.as_raw_fd();
+        // Safe because `fd` remains open while `port` is alive, and `termios`
+        // is initialized by `tcgetattr` before being read.
+        unsafe {
+            let mut termios = MaybeUninit::<libc::termios>::uninit();
+            if libc::tcgetattr(fd, termios.as_mut_ptr()) != 0 {
+                return Err(IoError::last_os_error());
+            }
+            let mut termios = termios.assume_init();
+            libc::cfmakeraw(&mut termios);
+            termios.c_cflag |= libc::CLOCAL | libc::CREAD;
+            if libc::cfsetspeed(&mut termios, self.baud_rate) != 0
+                || libc::tcsetattr(fd, libc::TCSANOW, &termios) != 0
+            {
+                return Err(IoError::last_os_error());
+            }
+            // Discard whatever was left over from a previous connection.
+            libc::tcflush(fd, libc::TCIOFLUSH);
+        }
+        Ok(port)
+    }
+
+    /// Sends the initial frame, and only reports being connected if that
+    /// succeeds.
+    fn handle_opened(&self, file: File) -> Option<OpenedPort> {
+        let mut port = OpenedPort {
+            file,
+            unsent_bytes: vec![],
+        };
+        if let Err(e) = port.write_frame(&self.context.initial_frame) {
+            println!(
+                "Failed to send the initial frame to serial port {}: {}",
+                self.path, e
+            );
+            return None;
+        }
+        println!("Opened serial port {}", self.path);
+        self.context
+            .report_connection_status(/* is_connected= */ true);
+        Some(port)
+    }
+
+    fn handle_closed(&self, error: IoError) -> Option<OpenedPort> {
+        println!("Closed serial port {}: {}", self.path, error);
+        self.context
+            .report_connection_status(/* is_connected= */ false);
+        None
+    }
+}
+
+impl Transport for SerialTransport {
+    fn send_frame(&self, frame: &[c_char]) {
+        let mut port = self.port.borrow_mut();
+        let Some(opened_port) = port.as_mut() else {
+            return;
+        };
+        if let Err(e) = opened_port.write_frame(frame) {
+            *port = self.handle_closed(e);
+        }
+    }
+
+    fn connect(&self, now: Instant) {
+        let mut port = self.port.borrow_mut();
+        match port.as_mut() {
+            Some(opened_port) => {
+                if let Err(e) =
+                    opened_port.poll().and_then(|()| opened_port.flush())
+                {
+                    *port = self.handle_closed(e);
+                }
+            }
+            None => {
+                if let Some(last_attempt_time) = self.last_attempt_time.get() {
+                    if now.saturating_duration_since(last_attempt_time)
+                        < self.reconnect_interval
+                    {
+                        return;
+                    }
+                }
+                self.last_attempt_time.set(Some(now));
+                match self.open_port() {
+                    Ok(file) => *port = self.handle_opened(file),
+                    Err(e) => println!(
+                        "Failed to open serial port {}: {}",
+                        self.path, e
+                    ),
+                }
+            }
+        }
+    }
+
+    fn is_connected(&self) -> bool {
+        self.port.borrow().is_some()
+    }
+}
+
+fn get_speed(baud_rate: i32) -> Result<libc::speed_t> {
+    Ok(match baud_rate {
+        0 => return get_speed(DEFAULT_BAUD_RATE),
+        9600 => libc::B9600,
+        19200 => libc::B19200,
+        38400 => libc::B38400,
+        57600 => libc::B57600,
+        115200 => libc::B115200,
+        230400 => libc::B230400,
+        _ => bail!("Unsupported serial_baud_rate: {}", baud_rate),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::CStr;
+    use std::os::fd::FromRawFd;
+    use std::os::fd::OwnedFd;
+    use std::ptr;
+    use std::thread;
+
+    use super::*;
+    use crate::virtual_device::transport::testing::connect_until_status;
+    use crate::virtual_device::transport::testing::create_context;
+    use crate::virtual_device::transport::testing::INITIAL_FRAME;
+
+    const REPORT_FRAME: [c_char; 5] = [1, 3, 4, 5, 6];
+
+    /// Returns the main side of a pseudo-terminal pair, the subordinate side
+    /// which must be kept open, and the path of the subordinate side.
+    fn open_pty() -> (File, OwnedFd, String) {
+        let mut main_fd = -1;
+        let mut subordinate_fd = -1;
+        // Safe because both pointers point to valid integers, and the name is
+        // read right after `openpty()` succeeds.
+        unsafe {
+            assert_eq!(
+                libc::openpty(
+                    &mut main_fd,
+                    &mut subordinate_fd,
+                    ptr::null_mut(),
+                    ptr::null(),
+                    ptr::null(),
+                ),
+                0
+            );
+            let path = CStr::from_ptr(libc::ttyname(subordinate_fd))
+                .to_str()
+                .unwrap()
+                .to_string();
+            (
+                File::from_raw_fd(main_fd),
+                OwnedFd::from_raw_fd(subordinate_fd),
+                path,
+            )
+        }
+    }
+
+    fn read_frame(main: &mut File, length: usize) -> Vec<c_char> {
+        let mut buffer = vec![0u8; length];
+        main.read_exact(&mut buffer).unwrap();
+        buffer.into_iter().map(|byte| byte as c_char).collect()
+    }
+
+    #[test]
+    fn sends_initial_frame_then_reports() {
+        let (mut main, _subordinate, path) = open_pty();
+        let transport = SerialTransport::new(
+            &path,
+            /* baud_rate= */ 0,
+            Duration::from_millis(10),
+            create_context(),
+        )
+        .unwrap();
+
+        connect_until_status(&transport, /* is_connected= */ true);
+        transport.send_frame(&REPORT_FRAME);
+        transport.send_frame(&REPORT_FRAME);
+
+        assert_eq!(read_frame(&mut main, INITIAL_FRAME.len()), INITIAL_FRAME);
+        assert_eq!(read_frame(&mut main, REPORT_FRAME.len()), REPORT_FRAME);
+        assert_eq!(read_frame(&mut main, REPORT_FRAME.len()), REPORT_FRAME);
+    }
+
+    #[test]
+    fn keeps_frames_intact_when_port_is_full() {
+        let (mut main, _subordinate, path) = open_pty();
+        let transport = SerialTransport::new(
+            &path,
+            /* baud_rate= */ 0,
+            Duration::from_millis(10),
+            create_context(),
+        )
+        .unwrap();
+        connect_until_status(&transport, /* is_connected= */ true);
+        assert_eq!(read_frame(&mut main, INITIAL_FRAME.len()), INITIAL_FRAME);
+
+        // Nothing is read from the port until it stops taking more bytes.
+        let mut frames = vec![];
+        while transport
+            .port
+            .borrow()
+            .as_ref()
+            .unwrap()
+            .unsent_bytes
+            .is_empty()
+        {
+            let frame = [1, 3, 0, 0, frames.len() as c_char];
+            transport.send_frame(&frame);
+            frames.push(frame);
+        }
+        let reader = thread::spawn(move || {
+            read_frame(&mut main, frames.len() * 5)
+                .chunks(5)
+                .map(|frame| frame.to_vec())
+                .collect::<Vec<_>>()
+                == frames
+        });
+        while !transport
+            .port
+            .borrow()
+            .as_ref()
+            .unwrap()
+            .unsent_bytes
+            .is_empty()
+        {
+            transport.connect(Instant::now());
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        assert!(reader.join().unwrap());
+        assert!(transport.is_connected());
+    }
+
+    #[test]
+    fn rejects_unsupported_baud_rate() {
+        assert!(SerialTransport::new(
+            "/dev/null",
+            /* baud_rate= */ 12345,
+            Duration::from_millis(10),
+            create_context(),
+        )
+        .is_err());
+    }
+}
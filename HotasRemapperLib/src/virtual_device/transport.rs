@@ -0,0 +1,167 @@
+use std::ffi::c_char;
+use std::time::Duration;
+use std::time::Instant;
+
+use anyhow::bail;
+use anyhow::Result;
+
+use super::network_transport::NetworkProtocol;
+use super::network_transport::NetworkTransport;
+use super::rfcomm_transport::RfcommTransport;
+use super::serial_transport::SerialTransport;
+use crate::settings::virtual_device_settings::TransportType;
+use crate::settings::VirtualDeviceSettings;
+use crate::ConnectionStatusCallback;
+use crate::ConnectionType;
+
+const DEFAULT_RECONNECT_INTERVAL: Duration = Duration::from_millis(1000);
+
+/// What every transport is given when it's created.
+pub(crate) struct TransportContext {
+    /// Used to report whether the transport is connected, as
+    /// `ConnectionType::VirtualDevice`.
+    pub connection_status_callback: ConnectionStatusCallback,
+    /// Must be sent whenever the transport gets connected, before any other
+    /// frame.
+    pub initial_frame: Vec<c_char>,
+}
+
+impl TransportContext {
+    pub fn report_connection_status(&self, is_connected: bool) {
+        // Safe because the caller guarantees the callback remains a valid
+        // function pointer.
+        unsafe {
+            (self.connection_status_callback)(
+                ConnectionType::VirtualDevice,
+                is_connected,
+            )
+        };
+    }
+}
+
+/// The link to the receiver of the reports, e.g. the Raspberry Pi. Frames sent
+/// while disconnected are dropped.
+///
+/// Whenever transports get connected or disconnected, they report it through
+/// `TransportContext::connection_status_callback`, e.g. with
+/// `report_connection_status()`, and once connected, they send
+/// `TransportContext::initial_frame` before any other frame.
+pub(crate) trait Transport {
+    /// Sends one frame, i.e. a tagged report. Transports that find themselves
+    /// disconnected while sending must report it.
+    fn send_frame(&self, frame: &[c_char]);
+
+    /// Called periodically. Connects if disconnected and an attempt is due,
+    /// and otherwise checks that the connection is still alive. Transports
+    /// that are connected when notified by the system, as RFCOMM is, don't
+    /// need to do anything.
+    fn connect(&self, _now: Instant) {}
+
+    /// Returns whether the last reported status is connected, i.e. whether
+    /// frames can be sent.
+    fn is_connected(&self) -> bool;
+}
+
+pub(crate) fn create_transport(
+    settings: &VirtualDeviceSettings,
+    context: TransportContext,
+) -> Result<Box<dyn Transport>> {
+    Ok(match settings.transport_type.enum_value_or_default() {
+        TransportType::RFCOMM => {
+            Box::new(RfcommTransport::new(settings, context)?)
+        }
+        TransportType::TCP => Box::new(NetworkTransport::new(
+            &settings.network_address,
+            NetworkProtocol::Tcp,
+            get_reconnect_interval(settings)?,
+            context,
+        )?),
+        TransportType::UDP => Box::new(NetworkTransport::new(
+            &settings.network_address,
+            NetworkProtocol::Udp,
+            get_reconnect_interval(settings)?,
+            context,
+        )?),
+        TransportType::SERIAL => Box::new(SerialTransport::new(
+            &settings.serial_port_path,
+            settings.serial_baud_rate,
+            get_reconnect_interval(settings)?,
+            context,
+        )?),
+    })
+}
+
+fn get_reconnect_interval(
+    settings: &VirtualDeviceSettings,
+) -> Result<Duration> {
+    match settings.reconnect_interval_ms {
+        0 => Ok(DEFAULT_RECONNECT_INTERVAL),
+        interval_ms if interval_ms > 0 => {
+            Ok(Duration::from_millis(interval_ms as u64))
+        }
+        interval_ms => {
+            bail!(
+                "reconnect_interval_ms must not be negative: {}",
+                interval_ms
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod testing {
+    use std::cell::RefCell;
+    use std::collections::VecDeque;
+    use std::thread;
+    use std::time::Duration;
+    use std::time::Instant;
+
+    use super::*;
+
+    pub const INITIAL_FRAME: [c_char; 4] = [-1, 2, 0, 7];
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    thread_local! {
+        // Transports report their status on the thread that ticks them.
+        static CONNECTION_STATUSES: RefCell<VecDeque<bool>> =
+            Default::default();
+    }
+
+    unsafe extern "C" fn record_connection_status(
+        _connection_type: ConnectionType,
+        is_connected: bool,
+    ) {
+        CONNECTION_STATUSES
+            .with_borrow_mut(|statuses| statuses.push_back(is_connected));
+    }
+
+    pub fn create_context() -> TransportContext {
+        TransportContext {
+            connection_status_callback: record_connection_status,
+            initial_frame: INITIAL_FRAME.to_vec(),
+        }
+    }
+
+    /// Lets `transport` connect until it reports a connection status, and
+    /// checks that it's `is_connected`.
+    pub fn connect_until_status(transport: &dyn Transport, is_connected: bool) {
+        let start_time = Instant::now();
+        loop {
+            transport.connect(Instant::now());
+            if let Some(status) =
+                CONNECTION_STATUSES.with_borrow_mut(VecDeque::pop_front)
+            {
+                assert_eq!(status, is_connected);
+                assert_eq!(transport.is_connected(), is_connected);
+                return;
+            }
+            assert!(
+                start_time.elapsed() < TIMEOUT,
+                "Timed out waiting for is_connected={}",
+                is_connected
+            );
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+}
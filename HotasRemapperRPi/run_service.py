@@ -1,31 +1,192 @@
+import os
 import socket
+import subprocess
+import sys
 
-server = socket.socket(
-    socket.AF_BLUETOOTH,
-    socket.SOCK_STREAM,
-    socket.BTPROTO_RFCOMM)
-server.bind(('B8:27:EB:C7:5B:1D', 1))
-server.listen(1)
-
-print('Starting server')
-client, addr = server.accept()
-print('Accepted client')
-
-try:
-    with open('/dev/hidg0', 'wb') as device:
-        while True:
-            data = client.recv(7)
-            if data:
-                print(f'Message: {data}')
-                device.write(data)
-                device.flush()
-            else:
-                print('Exiting')
-                break
-
-except KeyboardInterrupt:
-    print('Received Ctrl-C')
-
-finally:
-    client.close()
-    server.close()
+# Each report is sent in a frame of:
+#   * 1 byte for the report ID, which is 0 if the device set up by
+#     `setup_virtual_keyboard` doesn't use report IDs.
+#   * 1 byte for the length of the report.
+#   * The report itself.
+FRAME_HEADER_LENGTH = 2
+
+# Instead of a report, this frame carries the device config, i.e. 1 byte for
+# whether the device is a boot protocol keyboard, 1 byte for the report length,
+# and then the report descriptor. It's sent whenever we are connected.
+DEVICE_CONFIG_REPORT_ID = 0xFF
+
+FUNCTION_PATH = '/sys/kernel/config/usb_gadget/virtual_keyboard/functions/hid.usb0'
+SETUP_SCRIPT = os.path.join(os.path.dirname(__file__), 'setup_virtual_keyboard')
+
+RFCOMM_ADDRESS = ('B8:27:EB:C7:5B:1D', 1)
+DEFAULT_NETWORK_PORT = 5555
+MAX_DATAGRAM_LENGTH = 1024
+
+
+def receive_exactly(client, length):
+    """Returns `length` bytes, or None if the connection is closed."""
+    data = b''
+    while len(data) < length:
+        chunk = client.recv(length - len(data))
+        if not chunk:
+            return None
+        data += chunk
+    return data
+
+
+def receive_frame(client):
+    """Returns the report ID and the report of one complete frame, or None if
+    the connection is closed."""
+    header = receive_exactly(client, FRAME_HEADER_LENGTH)
+    if header is None:
+        return None
+    report_id, report_length = header
+    report = receive_exactly(client, report_length)
+    if report is None:
+        return None
+    return report_id, report
+
+
+def parse_datagram(datagram):
+    """Returns the frames in a UDP datagram, which are always complete."""
+    frames = []
+    while len(datagram) >= FRAME_HEADER_LENGTH:
+        report_id, report_length = datagram[:FRAME_HEADER_LENGTH]
+        report = datagram[FRAME_HEADER_LENGTH:
+                          FRAME_HEADER_LENGTH + report_length]
+        if len(report) < report_length:
+            break
+        frames.append((report_id, report))
+        datagram = datagram[FRAME_HEADER_LENGTH + report_length:]
+    if datagram:
+        print(f'Dropping incomplete frame: {datagram}')
+    return frames
+
+
+def read_function_attribute(name):
+    try:
+        with open(f'{FUNCTION_PATH}/{name}', 'rb') as attribute:
+            return attribute.read()
+    except FileNotFoundError:
+        return None
+
+
+def set_up_device(device_config):
+    """Sets up the HID device if it doesn't match `device_config` yet."""
+    is_boot_keyboard = device_config[0] != 0
+    report_length = device_config[1]
+    report_desc = device_config[2:]
+    if (read_function_attribute('report_desc') == report_desc and
+            read_function_attribute('report_length') ==
+            f'{report_length}\n'.encode() and
+            read_function_attribute('protocol') ==
+            (b'1\n' if is_boot_keyboard else b'0\n')):
+        print('Device is already set up')
+        return
+    print('Setting up device')
+    args = [SETUP_SCRIPT, report_desc.hex(), str(report_length)]
+    if is_boot_keyboard:
+        args.append('boot')
+    subprocess.run(args, check=True)
+
+
+class Receiver:
+    """Writes the reports it receives to the HID device."""
+
+    def __init__(self):
+        self.device = None
+        self.device_config = None
+
+    def handle_frame(self, report_id, report):
+        if report_id == DEVICE_CONFIG_REPORT_ID:
+            # The UDP transport resends the device config periodically.
+            if report == self.device_config:
+                return
+            self.close()
+            set_up_device(report)
+            self.device = open('/dev/hidg0', 'wb')
+            self.device_config = report
+            return
+        if not self.device:
+            print('Dropping report received before device config')
+            return
+        data = bytes([report_id]) + report if report_id else report
+        print(f'Message: {data}')
+        self.device.write(data)
+        self.device.flush()
+
+    def close(self):
+        if self.device:
+            self.device.close()
+        self.device = None
+        self.device_config = None
+
+
+def serve_stream(server, receiver):
+    """Accepts one client at a time, so that the sender can reconnect."""
+    server.listen(1)
+    while True:
+        print('Waiting for client')
+        client, addr = server.accept()
+        print(f'Accepted client {addr}')
+        with client:
+            while True:
+                frame = receive_frame(client)
+                if not frame:
+                    print('Client disconnected')
+                    break
+                receiver.handle_frame(*frame)
+        # The device config is sent again once reconnected.
+        receiver.close()
+
+
+def serve_datagrams(server, receiver):
+    print('Waiting for datagrams')
+    while True:
+        datagram, _ = server.recvfrom(MAX_DATAGRAM_LENGTH)
+        for frame in parse_datagram(datagram):
+            receiver.handle_frame(*frame)
+
+
+def main():
+    """Usage: run_service.py [rfcomm|tcp|udp] [port]
+
+    The transport must match `transport_type` in the settings of HotasRemapper.
+    For TCP and UDP, this listens on all interfaces at `port`.
+    """
+    transport = sys.argv[1] if len(sys.argv) > 1 else 'rfcomm'
+    port = int(sys.argv[2]) if len(sys.argv) > 2 else DEFAULT_NETWORK_PORT
+    if transport == 'rfcomm':
+        server = socket.socket(
+            socket.AF_BLUETOOTH,
+            socket.SOCK_STREAM,
+            socket.BTPROTO_RFCOMM)
+        server.bind(RFCOMM_ADDRESS)
+    elif transport == 'tcp':
+        server = socket.socket(socket.AF_INET, socket.SOCK_STREAM)
+        server.setsockopt(socket.SOL_SOCKET, socket.SO_REUSEADDR, 1)
+        server.bind(('', port))
+    elif transport == 'udp':
+        server = socket.socket(socket.AF_INET, socket.SOCK_DGRAM)
+        server.bind(('', port))
+    else:
+        sys.exit(f'Unknown transport: {transport}')
+
+    print(f'Starting {transport} server')
+    receiver = Receiver()
+    try:
+        if transport == 'udp':
+            serve_datagrams(server, receiver)
+        else:
+            serve_stream(server, receiver)
+
+    except KeyboardInterrupt:
+        print('Received Ctrl-C')
+
+    finally:
+        receiver.close()
+        server.close()
+
+
+if __name__ == '__main__':
+    main()